@@ -0,0 +1,123 @@
+use crate::*;
+use std::time::Duration;
+
+/// Types that can be encoded as command arguments. Bytes and strings are sent as is, numbers in decimal, `Duration` in
+/// whole milliseconds to pair with `PX`, `PEXPIRE` and friends. `None` is skipped, while slices, `Vec`s and pairs
+/// expand to one argument per element. `u8` is deliberately not an argument so `[u8]` is unambiguously bytes.
+pub trait ToArg {
+    /// append the RESP encoding of the arguments to `buf` and return how many arguments were written
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize;
+}
+
+fn write_bulk(buf: &mut Vec<u8>, x: &[u8]) -> usize {
+    write!(buf, "${}\r\n", x.len()).expect("bug");
+    buf.extend_from_slice(x);
+    buf.extend_from_slice(b"\r\n");
+    1
+}
+
+// format into a stack buffer, which is large enough for any integer and the shortest representation of any float
+fn write_number(buf: &mut Vec<u8>, x: impl std::fmt::Debug) -> usize {
+    let mut stack = [0; 48];
+    let mut cursor = &mut stack[..];
+    write!(cursor, "{:?}", x).expect("bug");
+    let len = 48 - cursor.len();
+    write_bulk(buf, &stack[..len])
+}
+
+impl<T: ToArg + ?Sized> ToArg for &T {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        (**self).write_args(buf)
+    }
+}
+
+impl ToArg for [u8] {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        write_bulk(buf, self)
+    }
+}
+
+impl<const N: usize> ToArg for [u8; N] {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        write_bulk(buf, self)
+    }
+}
+
+impl ToArg for Vec<u8> {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        write_bulk(buf, self)
+    }
+}
+
+impl ToArg for Box<[u8]> {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        write_bulk(buf, self)
+    }
+}
+
+impl ToArg for Bytes {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        write_bulk(buf, self)
+    }
+}
+
+impl ToArg for str {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        write_bulk(buf, self.as_bytes())
+    }
+}
+
+impl ToArg for String {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        write_bulk(buf, self.as_bytes())
+    }
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {$(
+        impl ToArg for $t {
+            fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+                write_number(buf, self)
+            }
+        }
+    )*}
+}
+
+// floats use the `Debug` form, which switches to exponents for very large or small values instead of printing hundreds of digits
+impl_number!(i8, i16, i32, i64, i128, isize, u16, u32, u64, u128, usize, f32, f64);
+
+impl ToArg for Duration {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        self.as_millis().write_args(buf)
+    }
+}
+
+impl<T: ToArg> ToArg for Option<T> {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        self.as_ref().map_or(0, |x| x.write_args(buf))
+    }
+}
+
+impl<T: ToArg> ToArg for [T] {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        self.iter().map(|x| x.write_args(buf)).sum()
+    }
+}
+
+impl<T: ToArg, const N: usize> ToArg for [T; N] {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        self[..].write_args(buf)
+    }
+}
+
+impl<T: ToArg> ToArg for Vec<T> {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        self[..].write_args(buf)
+    }
+}
+
+impl<A: ToArg, B: ToArg> ToArg for (A, B) {
+    fn write_args(&self, buf: &mut Vec<u8>) -> usize {
+        self.0.write_args(buf) + self.1.write_args(buf)
+    }
+}
@@ -1,5 +1,8 @@
 use crate::*;
 use std::borrow::Borrow;
+use std::ops::{RangeBounds, Bound};
+
+const SET_MANY_BATCH: usize = 512;
 
 /// BitVec is conceptually similar to Vec<bool>
 pub struct BitVec<A, C, K>
@@ -18,23 +21,61 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>> BitVec<A, C, K> where for<'a> &'a A
         self.client.arg(cmd).apply(|x| x.arg(self.key.borrow()).ignore())
     }
 
-    pub fn set_raw(&self, v: &[u8]) {
-        self.initiate(b"set").arg(v).fetch().ignore()
+    pub fn set_raw(&self, v: &[u8]) -> Result<(), RedisError> {
+        self.initiate(b"set").arg(v).fetch().map(|x| x.ignore())
+    }
+
+    /// get the underlying bytes. Bit 0 is the most significant bit of the first byte.
+    pub fn get_raw(&self) -> Result<Bytes, RedisError> {
+        match self.initiate(b"get").fetch()? {
+            Response::Bytes(x) => Ok(x),
+            Response::Nothing => Ok(Bytes::new()),
+            _ => unreachable!()
+        }
+    }
+
+    /// download the whole BitVec. The length is always a multiple of 8.
+    pub fn to_bitset(&self) -> Result<Vec<bool>, RedisError> {
+        let raw = self.get_raw()?;
+        Ok((0..raw.len() * 8).map(|i| raw[i / 8] & (0x80 >> (i % 8)) != 0).collect())
+    }
+
+    /// replace the whole BitVec with `bits` atomically.
+    pub fn from_bits(&self, bits: &[bool]) -> Result<(), RedisError> {
+        let mut raw = vec![0u8; bits.len().div_ceil(8)];
+        for (i, _) in bits.iter().enumerate().filter(|(_, x)| **x) {
+            raw[i / 8] |= 0x80 >> (i % 8)
+        }
+        self.set_raw(&raw)
     }
 
-    pub fn get_raw(&self) -> Box<[u8]> {
-        self.initiate(b"get").fetch().unwrap().bytes()
+    /// the indexes of all 1s, computed from a single download of the BitVec.
+    pub fn ones(&self) -> Result<impl Iterator<Item=usize>, RedisError> {
+        let raw = self.get_raw()?;
+        Ok((0..raw.len() * 8).filter(move |i| raw[i / 8] & (0x80 >> (i % 8)) != 0))
+    }
+
+    /// set the bits at `indexes` to `value`. Large inputs are sent in chunks, so the whole operation is not atomic.
+    pub fn set_many(&self, indexes: &[usize], value: bool) -> Result<(), RedisError> {
+        for chunk in indexes.chunks(SET_MANY_BATCH) {
+            let mut sess = self.initiate(b"bitfield");
+            for i in chunk {
+                sess.arg(b"set").arg(b"u1").arg(i).arg(if value { b"1" } else { b"0" });
+            }
+            sess.fetch()?.ignore()
+        }
+        Ok(())
     }
 
     /// get the bit value at `index` (starts from 0). If it is out of range or the key does not exist, return false.
     pub fn get(&self, index: usize) -> Result<bool, RedisError> {
-        self.initiate(b"getbit").arg(index.to_string().as_bytes()).fetch().map(|x| x.integer() != 0)
+        self.initiate(b"getbit").arg(index).fetch().map(|x| x.integer() != 0)
     }
 
     /// set the bit value at `index` (starts from 0).
     pub fn set(&self, index: usize, value: bool) -> Result<bool, RedisError> {
         self.initiate(b"setbit")
-            .arg(index.to_string().as_bytes())
+            .arg(index)
             .arg(if value { b"1" } else { b"0" })
             .fetch().map(|x| x.integer() != 0)
     }
@@ -48,6 +89,77 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>> BitVec<A, C, K> where for<'a> &'a A
         self.initiate(b"bitcount").fetch().map(|x| x.integer() as u64)
     }
 
+    /// count the number of 1 in `range`, in units of `unit`.
+    pub fn sum_range(&self, range: impl RangeBounds<usize>, unit: BitUnit) -> Result<u64, RedisError> {
+        let (start, end) = match bit_range(range) {
+            Some(x) => x,
+            None => return Ok(0)
+        };
+        self.initiate(b"bitcount")
+            .arg(start)
+            .arg(end)
+            .arg(unit.name())
+            .fetch().map(|x| x.integer() as u64)
+    }
+
+    /// return the index (in bits) of the first `value` in `range`, in units of `unit`.
+    ///
+    /// The end of `range` is always sent to BITPOS, so the data is never considered padded with 0: looking for `false`
+    /// when every bit in `range` is 1 returns None, also when `range` has no end.
+    pub fn find(&self, value: bool, range: impl RangeBounds<usize>, unit: BitUnit) -> Result<Option<usize>, RedisError> {
+        let (start, end) = match bit_range(range) {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+        self.initiate(b"bitpos")
+            .arg(if value { b"1" } else { b"0" })
+            .arg(start)
+            .arg(end)
+            .arg(unit.name())
+            .fetch().map(|x| {
+                let x = x.integer();
+                if x == -1 {
+                    None
+                } else {
+                    Some(x as _)
+                }
+            })
+    }
+
+    fn bitop(&self, op: &[u8], others: &[&dyn Collection], dest: &impl Collection) -> Result<usize, RedisError> {
+        let mut sess = self.client.arg(b"bitop");
+        sess.arg(op).arg(dest.key()).arg(self.key.borrow());
+        for x in others {
+            sess.arg(x.key());
+        }
+        sess.fetch().map(|x| x.integer() as _)
+    }
+
+    /// store the bitwise and of self and `others` into `dest`. Return the length of `dest` in bytes.
+    pub fn and(&self, others: &[&dyn Collection], dest: &impl Collection) -> Result<usize, RedisError> {
+        self.bitop(b"and", others, dest)
+    }
+
+    /// store the bitwise or of self and `others` into `dest`. Return the length of `dest` in bytes.
+    pub fn or(&self, others: &[&dyn Collection], dest: &impl Collection) -> Result<usize, RedisError> {
+        self.bitop(b"or", others, dest)
+    }
+
+    /// store the bitwise xor of self and `others` into `dest`. Return the length of `dest` in bytes.
+    pub fn xor(&self, others: &[&dyn Collection], dest: &impl Collection) -> Result<usize, RedisError> {
+        self.bitop(b"xor", others, dest)
+    }
+
+    /// store the bitwise not of self into `dest`. Return the length of `dest` in bytes.
+    pub fn not(&self, dest: &impl Collection) -> Result<usize, RedisError> {
+        self.bitop(b"not", &[], dest)
+    }
+
+    /// view the BitVec as an array of packed integers of type `ty`.
+    pub fn bitfield(&self, ty: BitFieldType) -> BitField<'_, A, C, K> {
+        BitField { bitvec: self, ty: ty.name().into_bytes(), overflow: Overflow::Wrap }
+    }
+
     /// return the index of the first 1. None if the BitVec is empty or contains only 0
     pub fn find_first(&self) -> Result<Option<usize>, RedisError> {
         self.initiate(b"bitpos").arg(b"1").fetch().map(|x| {
@@ -60,3 +172,115 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>> BitVec<A, C, K> where for<'a> &'a A
         })
     }
 }
+
+impl<A, C, K: Borrow<[u8]>> Collection for BitVec<A, C, K> {
+    fn key(&self) -> &[u8] {
+        self.key.borrow()
+    }
+}
+
+// convert to the inclusive bounds used by redis, where -1 is the end. Return None if the range is empty.
+fn bit_range(range: impl RangeBounds<usize>) -> Option<(i64, i64)> {
+    let start = match range.start_bound() {
+        Bound::Included(x) => *x as i64,
+        Bound::Excluded(x) => *x as i64 + 1,
+        Bound::Unbounded => 0
+    };
+    let end = match range.end_bound() {
+        Bound::Included(x) => *x as i64,
+        Bound::Excluded(0) => return None,
+        Bound::Excluded(x) => *x as i64 - 1,
+        Bound::Unbounded => return Some((start, -1))
+    };
+    Some((start, end)).filter(|(start, end)| start <= end)
+}
+
+/// The unit of ranges in `sum_range` and `find`. `Bit` requires Redis 7.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitUnit { Byte, Bit }
+
+impl BitUnit {
+    fn name(self) -> &'static [u8] {
+        match self {
+            BitUnit::Byte => b"byte",
+            BitUnit::Bit => b"bit"
+        }
+    }
+}
+
+/// Integer types supported by `bitfield`. Signed integers can have 1 to 64 bits, unsigned integers 1 to 63 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldType { Signed(u8), Unsigned(u8) }
+
+impl BitFieldType {
+    fn name(self) -> String {
+        match self {
+            BitFieldType::Signed(x) => format!("i{}", x),
+            BitFieldType::Unsigned(x) => format!("u{}", x)
+        }
+    }
+}
+
+/// Behaviour of `set` and `increment` on overflows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// wrap around, which is the default
+    Wrap,
+    /// saturate to the min or max value
+    Sat,
+    /// do nothing and return None
+    Fail
+}
+
+impl Overflow {
+    fn name(self) -> &'static [u8] {
+        match self {
+            Overflow::Wrap => b"wrap",
+            Overflow::Sat => b"sat",
+            Overflow::Fail => b"fail"
+        }
+    }
+}
+
+/// A view of a BitVec as an array of packed integers. Indexes are in the unit of the integer width.
+pub struct BitField<'b, A, C, K> {
+    bitvec: &'b BitVec<A, C, K>,
+    ty: Vec<u8>,
+    overflow: Overflow
+}
+
+impl<'b, A, C: Deref<Target=A>, K: Borrow<[u8]>> BitField<'b, A, C, K> where for<'a> &'a A: AsRedis {
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    fn initiate(&self, cmd: &[u8], index: usize) -> Session<<&A as AsRedis>::P> {
+        self.bitvec.initiate(b"bitfield").apply(|x| x
+            .arg(b"overflow").arg(self.overflow.name())
+            .arg(cmd).arg(&self.ty).arg(format!("#{}", index).as_bytes())
+            .ignore())
+    }
+
+    pub fn get(&self, index: usize) -> Result<i64, RedisError> {
+        self.initiate(b"get", index).fetch().map(|x| x.list()[0].as_integer())
+    }
+
+    /// set the integer at `index` and return the old value. Return None if overflowed with `Overflow::Fail`.
+    pub fn set(&self, index: usize, value: i64) -> Result<Option<i64>, RedisError> {
+        self.initiate(b"set", index).arg(value).fetch().map(|x| match x.list().pop().unwrap() {
+            Response::Integer(x) => Some(x),
+            Response::Nothing => None,
+            _ => unreachable!()
+        })
+    }
+
+    /// increment the integer at `index` and return the new value. Return None if overflowed with `Overflow::Fail`.
+    pub fn increment(&self, index: usize, delta: i64) -> Result<Option<i64>, RedisError> {
+        self.initiate(b"incrby", index).arg(delta).fetch().map(|x| match x.list().pop().unwrap() {
+            Response::Integer(x) => Some(x),
+            Response::Nothing => None,
+            _ => unreachable!()
+        })
+    }
+}
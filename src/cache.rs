@@ -0,0 +1,320 @@
+use crate::*;
+use crate::tracking::listen_invalidations;
+use std::borrow::Borrow;
+use std::collections::{HashMap, BTreeMap};
+use std::time::{Duration, Instant};
+
+// read the value and its ttl atomically, so the ttl belongs to the value
+static GET_WITH_TTL: Script = Script::from_static("return {redis.call('get', KEYS[1]), redis.call('pttl', KEYS[1])}");
+
+/// Cache stores each entry as a separate key under the prefix with a ttl. An optional local LRU tier keeps recently
+/// used entries in memory, which are evicted when other clients modify them.
+pub struct Cache<A, C, K, F, V>
+{
+    client: C,
+    prefix: K,
+    ttl: Duration,
+    field_serializer: fn(x: &F) -> Box<[u8]>,
+    value_serializer: fn(x: &V) -> Box<[u8]>,
+    value_deserializer: fn(x: &[u8]) -> V,
+    local: Option<Arc<Mutex<Local<V>>>>,
+    publish: bool,
+    inflight: Mutex<HashMap<Box<[u8]>, Arc<Flight>>>,
+    phantom: std::marker::PhantomData<A>
+}
+
+/// How the local tier learns about modifications made by other clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invalidation {
+    /// writers publish modified keys to a channel. All writers must use `publish_invalidations` or a local tier.
+    PubSub,
+    /// Redis reports all modifications under the prefix with CLIENT TRACKING in broadcasting mode. Requires Redis 6.0.
+    Tracking
+}
+
+impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, F, V: Clone> Cache<A, C, K, F, V> where for<'a> &'a A: AsRedis {
+    pub fn new(
+        client: C, prefix: K, ttl: Duration,
+        field_serializer: fn(x: &F) -> Box<[u8]>,
+        value_serializer: fn(x: &V) -> Box<[u8]>,
+        value_deserializer: fn(x: &[u8]) -> V
+    ) -> Self {
+        Self {
+            client, prefix, ttl, field_serializer, value_serializer, value_deserializer,
+            local: None, publish: false, inflight: Mutex::new(HashMap::new()), phantom: std::marker::PhantomData
+        }
+    }
+
+    /// enable the local tier of at most `capacity` entries. `conn` is a dedicated connection that is moved into a background
+    /// thread listening for invalidations, which exits when the Cache is dropped and the next message arrives.
+    pub fn with_local<T: Read + Write + Send + 'static>(mut self, capacity: usize, mut conn: T, mode: Invalidation) -> Result<Self, RedisError> where V: Send + 'static {
+        let channel = match mode {
+            Invalidation::PubSub => {
+                self.publish = true;
+                self.channel()
+            },
+            Invalidation::Tracking => {
+                let id = Session::new(&mut conn).arg(b"client").arg(b"id").fetch()?.integer();
+                Session::new(&mut conn).arg(b"client").arg(b"tracking").arg(b"on")
+                    .arg(b"redirect").arg(id)
+                    .arg(b"bcast").arg(b"prefix").arg(self.prefix.borrow())
+                    .fetch()?.ignore();
+                b"__redis__:invalidate"[..].into()
+            }
+        };
+        Session::new(&mut conn).arg(b"subscribe").arg(&channel).fetch()?.ignore();
+
+        let local = Arc::new(Mutex::new(Local { lru: Lru::new(capacity), filling: HashMap::new() }));
+        let weak = Arc::downgrade(&local);
+        listen_invalidations(conn, move |keys| match weak.upgrade() {
+            Some(local) => {
+                let mut local = local.lock().unwrap();
+                match keys {
+                    Some(keys) => for key in keys {
+                        local.invalidate(&key)
+                    },
+                    None => local.clear()
+                }
+                true
+            },
+            None => false
+        });
+
+        self.local = Some(local);
+        Ok(self)
+    }
+
+    /// publish modifications for the local tiers of other clients using `Invalidation::PubSub`.
+    pub fn publish_invalidations(mut self) -> Self {
+        self.publish = true;
+        self
+    }
+
+    fn channel(&self) -> Box<[u8]> {
+        [self.prefix.borrow(), b"__invalidate"].concat().into()
+    }
+
+    fn full_key(&self, field: &F) -> Box<[u8]> {
+        [self.prefix.borrow(), &(self.field_serializer)(field)].concat().into()
+    }
+
+    fn invalidate(&self, key: &[u8]) -> Result<(), RedisError> {
+        if let Some(local) = &self.local {
+            local.lock().unwrap().invalidate(key)
+        }
+        if self.publish {
+            self.client.arg(b"publish").arg(self.channel()).arg(key).fetch()?.ignore()
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, field: impl Borrow<F>) -> Result<Option<V>, RedisError> {
+        let key = self.full_key(field.borrow());
+        let local = match &self.local {
+            Some(local) => local,
+            None => return match self.client.arg(b"get").arg(&key).fetch()? {
+                Response::Bytes(x) => Ok(Some((self.value_deserializer)(&x))),
+                Response::Nothing => Ok(None),
+                _ => unreachable!()
+            }
+        };
+
+        {
+            let mut local = local.lock().unwrap();
+            if let Some(v) = local.lru.get(&key) {
+                return Ok(Some(v))
+            }
+            local.filling.entry(key.clone()).or_insert((0, false)).0 += 1;
+        }
+
+        // also read the ttl so the local entry does not outlive the remote one
+        let res = GET_WITH_TTL.invoke::<(Option<Bytes>, i64)>(&*self.client, &[&RawKey(&key)], &[])
+            .map(|(value, ttl)| (value.map(|x| (self.value_deserializer)(&x)), ttl));
+
+        // skip the local entry if the key is modified after we read it, which would be stale until it expires
+        let mut local = local.lock().unwrap();
+        let invalidated = local.end_fill(&key);
+        match res? {
+            (Some(value), ttl) => {
+                if ttl > 0 && !invalidated {
+                    local.lru.insert(key, value.clone(), Some(Instant::now() + Duration::from_millis(ttl as _)));
+                }
+                Ok(Some(value))
+            },
+            (None, _) => Ok(None)
+        }
+    }
+
+    pub fn insert(&self, field: impl Borrow<F>, value: V) -> Result<(), RedisError> {
+        let key = self.full_key(field.borrow());
+        self.client.arg(b"set").arg(&key).arg((self.value_serializer)(&value))
+            .arg(b"px").arg(self.ttl.as_millis())
+            .fetch()?.ignore();
+        self.invalidate(&key)?;
+        if let Some(local) = &self.local {
+            local.lock().unwrap().lru.insert(key, value, Some(Instant::now() + self.ttl));
+        }
+        Ok(())
+    }
+
+    pub fn remove(&self, field: impl Borrow<F>) -> Result<(), RedisError> {
+        let key = self.full_key(field.borrow());
+        self.client.arg(b"del").arg(&key).fetch()?.ignore();
+        self.invalidate(&key)
+    }
+
+    /// get the value, or load and insert it if missing. Concurrent calls for the same field in this process
+    /// wait for a single loader instead of calling their own.
+    pub fn get_or_insert_with(&self, field: impl Borrow<F>, loader: impl FnOnce() -> V) -> Result<V, RedisError> {
+        let key = self.full_key(field.borrow());
+        loop {
+            if let Some(v) = self.get(field.borrow())? {
+                return Ok(v)
+            }
+
+            let (flight, leader) = {
+                let mut inflight = self.inflight.lock().unwrap();
+                match inflight.get(&key) {
+                    Some(flight) => (flight.clone(), false),
+                    None => {
+                        let flight = Arc::new(Flight { done: Mutex::new(false), cond: Condvar::new() });
+                        inflight.insert(key.clone(), flight.clone());
+                        (flight, true)
+                    }
+                }
+            };
+
+            if leader {
+                let _landing = Landing { cache: &self.inflight, key: &key, flight: &flight };
+                let value = loader();
+                self.insert(field.borrow(), value.clone())?;
+                return Ok(value)
+            }
+
+            // wait for the leader, then try to read again. If the leader failed, one of the waiters becomes the new leader
+            let mut done = flight.done.lock().unwrap();
+            while !*done {
+                done = flight.cond.wait(done).unwrap()
+            }
+        }
+    }
+}
+
+// the local tier and the keys being read into it
+struct Local<V> {
+    lru: Lru<V>,
+    filling: HashMap<Box<[u8]>, (usize, bool)> // key -> (number of reads in progress, invalidated)
+}
+
+impl<V: Clone> Local<V> {
+    fn invalidate(&mut self, key: &[u8]) {
+        self.lru.remove(key);
+        if let Some((_, invalidated)) = self.filling.get_mut(key) {
+            *invalidated = true
+        }
+    }
+
+    fn clear(&mut self) {
+        self.lru.clear();
+        for (_, invalidated) in self.filling.values_mut() {
+            *invalidated = true
+        }
+    }
+
+    // finish a read and return whether the key is invalidated since it started
+    fn end_fill(&mut self, key: &[u8]) -> bool {
+        let (reading, invalidated) = self.filling.get_mut(key).unwrap();
+        let invalidated = *invalidated;
+        *reading -= 1;
+        if *reading == 0 {
+            self.filling.remove(key);
+        }
+        invalidated
+    }
+}
+
+struct Flight {
+    done: Mutex<bool>,
+    cond: Condvar
+}
+
+// wakes up the waiters when the leader finishes, including returning an error or panicking
+struct Landing<'a> {
+    cache: &'a Mutex<HashMap<Box<[u8]>, Arc<Flight>>>,
+    key: &'a [u8],
+    flight: &'a Flight
+}
+
+impl Drop for Landing<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut inflight) = self.cache.lock() {
+            inflight.remove(self.key);
+        }
+        if let Ok(mut done) = self.flight.done.lock() {
+            *done = true;
+        }
+        self.flight.cond.notify_all()
+    }
+}
+
+// a map that evicts the least recently used entries when full. Entries may also have an expiration time.
+pub(crate) struct Lru<V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<Box<[u8]>, (V, Option<Instant>, u64)>,
+    order: BTreeMap<u64, Box<[u8]>>
+}
+
+impl<V: Clone> Lru<V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { capacity, tick: 0, entries: HashMap::new(), order: BTreeMap::new() }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn get(&mut self, key: &[u8]) -> Option<V> {
+        let (value, expire, tick) = self.entries.get_mut(key)?;
+        if expire.map(|x| x <= Instant::now()).unwrap_or(false) {
+            self.remove(key);
+            return None
+        }
+
+        self.tick += 1;
+        let entry = self.order.remove(tick).unwrap();
+        self.order.insert(self.tick, entry);
+        *tick = self.tick;
+        Some(value.clone())
+    }
+
+    /// return the evicted entries
+    pub(crate) fn insert(&mut self, key: Box<[u8]>, value: V, expire: Option<Instant>) -> Vec<(Box<[u8]>, V)> {
+        let mut evicted = vec![];
+        self.remove(&key);
+        if self.capacity == 0 {
+            return evicted
+        }
+        while self.entries.len() >= self.capacity {
+            let (_, oldest) = self.order.pop_first().unwrap();
+            let (value, _, _) = self.entries.remove(&oldest).unwrap();
+            evicted.push((oldest, value))
+        }
+
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, expire, self.tick));
+        evicted
+    }
+
+    pub(crate) fn remove(&mut self, key: &[u8]) -> Option<V> {
+        let (value, _, tick) = self.entries.remove(key)?;
+        self.order.remove(&tick);
+        Some(value)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear()
+    }
+}
@@ -1,5 +1,9 @@
 use crate::*;
 use std::borrow::Borrow;
+use std::time::Duration;
+
+static REMOVE_IF_EQ: Script = Script::from_static("if redis.call('get', KEYS[1]) == ARGV[1] then return redis.call('del', KEYS[1]) else return 0 end");
+static EXPIRE_IF_EQ: Script = Script::from_static("if redis.call('get', KEYS[1]) == ARGV[1] then return redis.call('pexpire', KEYS[1], ARGV[2]) else return 0 end");
 
 /// Cell is a container that can hold only one value.
 pub struct Cell<A, C, K, T>
@@ -21,14 +25,60 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> Cell<A, C, K, T> where for<'a> &
     }
 
     pub fn set(&self, v: impl Borrow<T>) -> Result<(), RedisError> {
-        self.initiate(b"set").arg(&(self.serializer)(v.borrow())).fetch().map(|x| x.ignore())
+        self.initiate(b"set").arg((self.serializer)(v.borrow())).fetch().map(|x| x.ignore())
     }
 
     pub fn get(&self) -> Result<T, RedisError> {
         self.initiate(b"get").fetch().map(|x| (self.deserializer)(&x.bytes()))
     }
 
+    /// stream the raw value to `w`, for values too large to hold in memory. Return its length, or None if it is not set.
+    pub fn get_to_writer(&self, w: &mut impl Write) -> Result<Option<usize>, RedisError> {
+        self.initiate(b"get").fetch_into(w)
+    }
+
+    /// set the raw value from the next `len` bytes of `r`.
+    pub fn set_from_reader(&self, r: &mut impl Read, len: u64) -> Result<(), RedisError> {
+        let mut sess = self.initiate(b"set");
+        sess.send_from(r, len)?;
+        sess.recv().map(|x| x.ignore())
+    }
+
+    /// set the value only if the Cell is empty, and let it expire after `ttl`. Return whether it was set.
+    pub fn set_if_absent(&self, v: impl Borrow<T>, ttl: Option<Duration>) -> Result<bool, RedisError> {
+        let mut sess = self.initiate(b"set");
+        sess.arg((self.serializer)(v.borrow())).arg(b"nx");
+        if let Some(ttl) = ttl {
+            sess.arg(b"px").arg(check_ttl(ttl)?);
+        }
+        sess.fetch().map(|x| !x.is_nothing())
+    }
+
+    /// atomically clear the Cell if it holds `v`. Return whether it was cleared.
+    pub fn remove_if_eq(&self, v: impl Borrow<T>) -> Result<bool, RedisError> {
+        REMOVE_IF_EQ.invoke(&*self.client, &[self], &[&(self.serializer)(v.borrow())])
+    }
+
+    /// atomically let the Cell expire after `ttl` if it holds `v`. Return whether the ttl was set.
+    pub fn expire_if_eq(&self, v: impl Borrow<T>, ttl: Duration) -> Result<bool, RedisError> {
+        EXPIRE_IF_EQ.invoke(&*self.client, &[self], &[&(self.serializer)(v.borrow()), &check_ttl(ttl)?])
+    }
+
     pub fn clear(&self) -> Result<(), RedisError> {
         self.initiate(b"del").fetch().map(|x| x.ignore())
     }
 }
+
+impl<A, C, K: Borrow<[u8]>, T> Collection for Cell<A, C, K, T> {
+    fn key(&self) -> &[u8] {
+        self.key.borrow()
+    }
+}
+
+// ttls are sent in whole milliseconds, and 0 is rejected by set and deletes the key with pexpire
+fn check_ttl(ttl: Duration) -> Result<Duration, RedisError> {
+    if ttl < Duration::from_millis(1) {
+        return Err(RedisError::OtherError(format!("invalid ttl {:?}", ttl)))
+    }
+    Ok(ttl)
+}
@@ -0,0 +1,121 @@
+use crate::*;
+use std::borrow::Borrow;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const POLL_BATCH: usize = 512;
+const MAX_SLEEP: Duration = Duration::from_secs(1);
+
+// KEYS: schedule, target. ARGV: now, batch size
+static MOVE_DUE: Script = Script::from_static("
+local items = redis.call('zrangebyscore', KEYS[1], '-inf', ARGV[1], 'limit', 0, ARGV[2])
+if #items > 0 then
+    redis.call('zrem', KEYS[1], unpack(items))
+    redis.call('rpush', KEYS[2], unpack(items))
+end
+return #items");
+
+/// DelayedQueue holds items until their scheduled time, then moves them into a List. Items are members of a sorted set
+/// scored by the scheduled time, so scheduling an item that is already scheduled only changes its time.
+pub struct DelayedQueue<A, C, K, T>
+{
+    client: C,
+    key: K,
+    serializer: fn(x: &T) -> Box<[u8]>,
+    deserializer: fn(x: &[u8]) -> T,
+    phantom: std::marker::PhantomData<A>
+}
+
+impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> DelayedQueue<A, C, K, T> where for<'a> &'a A: AsRedis {
+    pub fn new(client: C, key: K, serializer: fn(x: &T) -> Box<[u8]>, deserializer: fn(x: &[u8]) -> T) -> Self {
+        Self { client, key, serializer, deserializer, phantom: std::marker::PhantomData }
+    }
+
+    fn initiate(&self, cmd: &[u8]) -> Session<<&A as AsRedis>::P> {
+        self.client.arg(cmd).apply(|x| x.arg(self.key.borrow()).ignore())
+    }
+
+    pub fn clear(&self) -> Result<(), RedisError> {
+        self.initiate(b"del").fetch().map(|x| x.ignore())
+    }
+
+    /// the number of items that are not moved yet
+    pub fn len(&self) -> Result<usize, RedisError> {
+        self.initiate(b"zcard").fetch().map(|x| x.integer() as _)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, RedisError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// make the item available at `at`, according to the clock of this machine.
+    pub fn schedule(&self, x: impl Borrow<T>, at: SystemTime) -> Result<(), RedisError> {
+        self.initiate(b"zadd")
+            .arg(to_millis(at))
+            .arg((self.serializer)(x.borrow()))
+            .fetch().map(|x| x.ignore())
+    }
+
+    pub fn schedule_after(&self, x: impl Borrow<T>, delay: Duration) -> Result<(), RedisError> {
+        self.schedule(x, SystemTime::now() + delay)
+    }
+
+    /// remove a scheduled item. Return false if it is not scheduled or already moved.
+    pub fn cancel(&self, x: impl Borrow<T>) -> Result<bool, RedisError> {
+        self.initiate(b"zrem").arg((self.serializer)(x.borrow())).fetch().map(|x| x.integer() == 1)
+    }
+
+    /// the earliest scheduled item and its time
+    pub fn peek(&self) -> Result<Option<(T, SystemTime)>, RedisError> {
+        let mut res = self.initiate(b"zrange").arg(b"0").arg(b"0").arg(b"withscores").fetch()?.list().into_iter();
+        match (res.next(), res.next()) {
+            (Some(item), Some(score)) => {
+                let at = UNIX_EPOCH + Duration::from_millis(parse_float(&score.bytes())? as _);
+                Ok(Some(((self.deserializer)(&item.bytes()), at)))
+            },
+            _ => Ok(None)
+        }
+    }
+
+    /// atomically move all due items into the list at `target`, in the order of their scheduled time. Items are pushed to
+    /// the key of `target` on the server of the queue, whatever client `target` uses. Return the number of moved items.
+    pub fn poll_due(&self, target: &impl Collection) -> Result<usize, RedisError> {
+        let now = to_millis(SystemTime::now());
+        let mut count = 0;
+        loop {
+            let moved = MOVE_DUE.invoke::<i64>(&*self.client, &[self, target], &[&now, &POLL_BATCH])? as usize;
+            count += moved;
+            if moved < POLL_BATCH {
+                return Ok(count)
+            }
+        }
+    }
+
+    /// like `poll_due`, but sleep until at least one item is moved or the timeout reached. Items scheduled by others
+    /// while sleeping are noticed within one second.
+    pub fn wait_due(&self, target: &impl Collection, timeout: Duration) -> Result<usize, RedisError> {
+        let deadline = SystemTime::now() + timeout;
+        loop {
+            let moved = self.poll_due(target)?;
+            let now = SystemTime::now();
+            if moved > 0 || now >= deadline {
+                return Ok(moved)
+            }
+
+            let next = match self.peek()? {
+                Some((_, at)) => at.min(deadline),
+                None => deadline
+            };
+            std::thread::sleep(next.duration_since(now).unwrap_or_default().min(MAX_SLEEP))
+        }
+    }
+}
+
+impl<A, C, K: Borrow<[u8]>, T> Collection for DelayedQueue<A, C, K, T> {
+    fn key(&self) -> &[u8] {
+        self.key.borrow()
+    }
+}
+
+fn to_millis(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as _
+}
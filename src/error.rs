@@ -0,0 +1,164 @@
+use crate::*;
+
+#[derive(Debug)]
+pub enum RedisError {
+    /// RESP protocol error
+    ProtocolError(&'static str),
+    /// Error returned by Redis
+    RedisError(ServerError),
+    /// IO Error in communication with Redis
+    IOError(std::io::Error),
+    /// Errors that you are unlikely to handle by code
+    OtherError(String)
+}
+
+impl RedisError {
+    /// the kind of the error returned by Redis, if it is one
+    pub fn kind(&self) -> Option<&ServerErrorKind> {
+        match self {
+            RedisError::RedisError(e) => Some(&e.kind),
+            _ => None
+        }
+    }
+
+    /// whether the same command may succeed if tried again later, possibly on a new connection or another node.
+    /// Note that a command that failed with an IO error may or may not have been executed.
+    pub fn is_retriable(&self) -> bool {
+        use std::io::ErrorKind::*;
+        match self {
+            RedisError::RedisError(e) => matches!(e.kind, ServerErrorKind::Moved(_) | ServerErrorKind::Ask(_) | ServerErrorKind::TryAgain |
+                ServerErrorKind::Loading | ServerErrorKind::Busy | ServerErrorKind::ClusterDown | ServerErrorKind::MasterDown),
+            RedisError::IOError(e) => matches!(e.kind(), ConnectionRefused | ConnectionReset | ConnectionAborted | NotConnected |
+                BrokenPipe | TimedOut | Interrupted | UnexpectedEof),
+            _ => false
+        }
+    }
+
+    /// whether the connection is broken or out of sync and should be dropped
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, RedisError::IOError(_) | RedisError::ProtocolError(_))
+    }
+}
+
+impl std::fmt::Display for RedisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RedisError::ProtocolError(e) => write!(f, "protocol error: {}", e),
+            RedisError::RedisError(e) => write!(f, "redis error: {}", e),
+            RedisError::IOError(e) => write!(f, "io error: {}", e),
+            RedisError::OtherError(e) => f.write_str(e)
+        }
+    }
+}
+
+impl std::error::Error for RedisError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RedisError::RedisError(e) => Some(e),
+            RedisError::IOError(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for RedisError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IOError(e)
+    }
+}
+
+impl From<ServerError> for RedisError {
+    fn from(e: ServerError) -> Self {
+        Self::RedisError(e)
+    }
+}
+
+/// An error reply, e.g. `-WRONGTYPE Operation against a key holding the wrong kind of value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerError {
+    pub kind: ServerErrorKind,
+    /// the whole error line, including the code
+    pub message: String
+}
+
+/// The code at the start of an error reply
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerErrorKind {
+    Err,
+    WrongType,
+    /// the key belongs to a slot served by another node
+    Moved(Redirect),
+    /// the key is being migrated, ask the other node once
+    Ask(Redirect),
+    NoScript,
+    Busy,
+    BusyKey,
+    Loading,
+    ReadOnly,
+    NoAuth,
+    WrongPass,
+    NoPerm,
+    ExecAbort,
+    TryAgain,
+    CrossSlot,
+    ClusterDown,
+    MasterDown,
+    NoReplicas,
+    Oom,
+    /// codes not listed above, e.g. those from modules
+    Other(String)
+}
+
+/// The target of a MOVED or ASK redirection
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub slot: u16,
+    pub addr: String
+}
+
+impl ServerError {
+    /// parse an error line without the leading `-`
+    pub fn parse(line: &str) -> Self {
+        let mut words = line.split(' ');
+        let code = words.next().unwrap_or_default();
+        let mut redirect = || -> Option<Redirect> {
+            Some(Redirect { slot: words.next()?.parse().ok()?, addr: words.next()?.to_string() })
+        };
+        let kind = match code {
+            "ERR" => ServerErrorKind::Err,
+            "WRONGTYPE" => ServerErrorKind::WrongType,
+            "MOVED" => redirect().map(ServerErrorKind::Moved).unwrap_or_else(|| ServerErrorKind::Other(code.to_string())),
+            "ASK" => redirect().map(ServerErrorKind::Ask).unwrap_or_else(|| ServerErrorKind::Other(code.to_string())),
+            "NOSCRIPT" => ServerErrorKind::NoScript,
+            "BUSY" => ServerErrorKind::Busy,
+            "BUSYKEY" => ServerErrorKind::BusyKey,
+            "LOADING" => ServerErrorKind::Loading,
+            "READONLY" => ServerErrorKind::ReadOnly,
+            "NOAUTH" => ServerErrorKind::NoAuth,
+            "WRONGPASS" => ServerErrorKind::WrongPass,
+            "NOPERM" => ServerErrorKind::NoPerm,
+            "EXECABORT" => ServerErrorKind::ExecAbort,
+            "TRYAGAIN" => ServerErrorKind::TryAgain,
+            "CROSSSLOT" => ServerErrorKind::CrossSlot,
+            "CLUSTERDOWN" => ServerErrorKind::ClusterDown,
+            "MASTERDOWN" => ServerErrorKind::MasterDown,
+            "NOREPLICAS" => ServerErrorKind::NoReplicas,
+            "OOM" => ServerErrorKind::Oom,
+            x => ServerErrorKind::Other(x.to_string())
+        };
+        Self { kind, message: line.to_string() }
+    }
+
+    /// the message without the code
+    pub fn detail(&self) -> &str {
+        self.message.split_once(' ').map_or("", |x| x.1)
+    }
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ServerError {}
@@ -0,0 +1,109 @@
+use crate::*;
+use std::borrow::Borrow;
+
+/// Redis 7 Functions, available on any AsRedis client.
+pub trait Functions: AsRedis {
+    /// load a library from Lua source, which must start with a `#!lua name=<library>` line. Return the library name.
+    fn function_load(self, source: &str, replace: bool) -> Result<String, RedisError> {
+        let mut sess = self.arg(b"function");
+        sess.arg(b"load");
+        if replace {
+            sess.arg(b"replace");
+        }
+        sess.arg(source.as_bytes()).fetch().and_then(String::from_response)
+    }
+
+    fn function_delete(self, library: &str) -> Result<(), RedisError> {
+        self.arg(b"function").arg(b"delete").arg(library.as_bytes()).fetch().map(|x| x.ignore())
+    }
+
+    fn function_list(self) -> Result<Vec<LibraryInfo>, RedisError> {
+        self.arg(b"function").arg(b"list").fetch()?.list().into_iter().map(LibraryInfo::from_response).collect()
+    }
+
+    /// serialize all loaded libraries into an opaque payload for `function_restore`
+    fn function_dump(self) -> Result<Bytes, RedisError> {
+        self.arg(b"function").arg(b"dump").fetch().map(|x| x.bytes())
+    }
+
+    fn function_restore(self, payload: &[u8], policy: RestorePolicy) -> Result<(), RedisError> {
+        self.arg(b"function").arg(b"restore").arg(payload).arg(match policy {
+            RestorePolicy::Append => "append",
+            RestorePolicy::Replace => "replace",
+            RestorePolicy::Flush => "flush"
+        }).fetch().map(|x| x.ignore())
+    }
+
+    /// call a function with `keys` and `args` and convert the reply.
+    fn fcall<R: FromResponse>(self, function: &str, keys: &[&dyn Collection], args: &[&dyn ToArg]) -> Result<R, RedisError> {
+        fcall(self.arg(b"fcall"), function, keys, args)
+    }
+
+    /// call a function that is declared with the `no-writes` flag, which can be served by replicas.
+    fn fcall_ro<R: FromResponse>(self, function: &str, keys: &[&dyn Collection], args: &[&dyn ToArg]) -> Result<R, RedisError> {
+        fcall(self.arg(b"fcall_ro"), function, keys, args)
+    }
+
+    /// like `fcall`, but `args` are encoded with `serializer`, e.g. the value serializer of a collection in `keys`.
+    fn fcall_with<V, R: FromResponse>(self, function: &str, keys: &[&dyn Collection], serializer: fn(x: &V) -> Box<[u8]>, args: &[impl Borrow<V>]) -> Result<R, RedisError> {
+        let args: Vec<_> = args.iter().map(|x| serializer(x.borrow())).collect();
+        fcall(self.arg(b"fcall"), function, keys, &args)
+    }
+
+    /// like `fcall_ro`, but `args` are encoded with `serializer`.
+    fn fcall_ro_with<V, R: FromResponse>(self, function: &str, keys: &[&dyn Collection], serializer: fn(x: &V) -> Box<[u8]>, args: &[impl Borrow<V>]) -> Result<R, RedisError> {
+        let args: Vec<_> = args.iter().map(|x| serializer(x.borrow())).collect();
+        fcall(self.arg(b"fcall_ro"), function, keys, &args)
+    }
+}
+
+impl<T: AsRedis> Functions for T {}
+
+fn fcall<T: Read + Write, P: DerefMut<Target=T>, R: FromResponse>(mut sess: Session<P>, function: &str, keys: &[&dyn Collection], args: &[impl ToArg]) -> Result<R, RedisError> {
+    sess.arg(function.as_bytes()).arg(keys.len());
+    for key in keys {
+        sess.arg(key.key());
+    }
+    sess.arg(args).fetch().and_then(R::from_response)
+}
+
+/// How `function_restore` handles existing libraries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestorePolicy {
+    /// fail if any library already exists, which is the default
+    Append,
+    /// replace existing libraries with the same names
+    Replace,
+    /// delete all existing libraries before restoring
+    Flush
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryInfo {
+    pub name: String,
+    pub engine: String,
+    pub functions: Vec<String>
+}
+
+impl FromResponse for LibraryInfo {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        let mut info = LibraryInfo { name: String::new(), engine: String::new(), functions: vec![] };
+        let mut pairs = Vec::<Response>::from_response(x)?.into_iter();
+        while let (Some(k), Some(v)) = (pairs.next(), pairs.next()) {
+            match &String::from_response(k)?[..] {
+                "library_name" => info.name = String::from_response(v)?,
+                "engine" => info.engine = String::from_response(v)?,
+                "functions" => for f in Vec::<Response>::from_response(v)? {
+                    let mut f = Vec::<Response>::from_response(f)?.into_iter();
+                    while let (Some(k), Some(v)) = (f.next(), f.next()) {
+                        if String::from_response(k)? == "name" {
+                            info.functions.push(String::from_response(v)?)
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
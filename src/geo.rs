@@ -0,0 +1,231 @@
+use crate::*;
+use std::borrow::Borrow;
+
+/// Geo is a set of members with locations
+pub struct Geo<A, C, K, T>
+{
+    client: C,
+    key: K,
+    serializer: fn(x: &T) -> Box<[u8]>,
+    deserializer: fn(x: &[u8]) -> T,
+    phantom: std::marker::PhantomData<A>
+}
+
+impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> Geo<A, C, K, T> where for<'a> &'a A: AsRedis {
+    pub fn new(client: C, key: K, serializer: fn(x: &T) -> Box<[u8]>, deserializer: fn(x: &[u8]) -> T) -> Self {
+        Self { client, key, serializer, deserializer, phantom: std::marker::PhantomData }
+    }
+
+    fn initiate(&self, cmd: &[u8]) -> Session<<&A as AsRedis>::P> {
+        self.client.arg(cmd).apply(|x| x.arg(self.key.borrow()).ignore())
+    }
+
+    pub fn clear(&self) -> Result<(), RedisError> {
+        self.initiate(b"del").fetch().map(|x| x.ignore())
+    }
+
+    /// add or update the location of a member. Return whether it is newly added.
+    pub fn insert(&self, member: impl Borrow<T>, location: Coordinates) -> Result<bool, RedisError> {
+        self.initiate(b"geoadd")
+            .arg(location.longitude)
+            .arg(location.latitude)
+            .arg((self.serializer)(member.borrow()))
+            .fetch().map(|x| x.integer() == 1)
+    }
+
+    /// return the number of newly added members
+    pub fn extend(&self, x: &[(impl Borrow<T>, Coordinates)]) -> Result<usize, RedisError> {
+        if x.is_empty() {
+            return Ok(0)
+        }
+
+        let mut sess = self.initiate(b"geoadd");
+        for (member, location) in x {
+            sess.arg(location.longitude)
+                .arg(location.latitude)
+                .arg((self.serializer)(member.borrow()));
+        }
+        sess.fetch().map(|x| x.integer() as _)
+    }
+
+    pub fn remove(&self, member: impl Borrow<T>) -> Result<(), RedisError> {
+        self.initiate(b"zrem").arg((self.serializer)(member.borrow())).fetch().map(|x| x.ignore())
+    }
+
+    pub fn len(&self) -> Result<usize, RedisError> {
+        self.initiate(b"zcard").fetch().map(|x| x.integer() as _)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, RedisError> {
+        Ok(self.len()? == 0)
+    }
+
+    pub fn position(&self, member: impl Borrow<T>) -> Result<Option<Coordinates>, RedisError> {
+        let mut res = self.initiate(b"geopos").arg((self.serializer)(member.borrow())).fetch()?.list();
+        match res.pop().unwrap() {
+            Response::List(x) => Coordinates::from_response(x).map(Some),
+            Response::Nothing => Ok(None),
+            _ => unreachable!()
+        }
+    }
+
+    /// the distance between two members. None if either of them does not exist.
+    pub fn distance(&self, a: impl Borrow<T>, b: impl Borrow<T>, unit: Unit) -> Result<Option<f64>, RedisError> {
+        match self.initiate(b"geodist")
+            .arg((self.serializer)(a.borrow()))
+            .arg((self.serializer)(b.borrow()))
+            .arg(unit.name())
+            .fetch()? {
+            Response::Bytes(x) => parse_float(&x).map(Some),
+            Response::Nothing => Ok(None),
+            _ => unreachable!()
+        }
+    }
+
+    fn search_args(&self, sess: &mut Session<<&A as AsRedis>::P>, center: GeoCenter<'_, T>, shape: GeoShape) {
+        match center {
+            GeoCenter::Member(x) => sess.arg(b"frommember").arg((self.serializer)(x)),
+            GeoCenter::Point(x) => sess.arg(b"fromlonlat")
+                .arg(x.longitude)
+                .arg(x.latitude)
+        };
+        match shape {
+            GeoShape::Radius(r, unit) => sess.arg(b"byradius").arg(r).arg(unit.name()),
+            GeoShape::Box(width, height, unit) => sess.arg(b"bybox")
+                .arg(width)
+                .arg(height)
+                .arg(unit.name())
+        };
+    }
+
+    /// search members within `shape` around `center`. Requires Redis 6.2.
+    pub fn search(&self, center: GeoCenter<'_, T>, shape: GeoShape, options: &GeoSearchOptions) -> Result<Vec<GeoMatch<T>>, RedisError> {
+        let mut sess = self.initiate(b"geosearch");
+        self.search_args(&mut sess, center, shape);
+        options.write_args(&mut sess);
+        if options.with_dist {
+            sess.arg(b"withdist");
+        }
+        if options.with_coord {
+            sess.arg(b"withcoord");
+        }
+
+        sess.fetch()?.list().into_iter().map(|x| match x {
+            Response::Bytes(x) => Ok(GeoMatch { member: (self.deserializer)(&x), distance: None, coordinates: None }),
+            Response::List(x) => {
+                let mut x = x.into_iter();
+                let member = (self.deserializer)(&x.next().unwrap().bytes());
+                let distance = if options.with_dist { Some(parse_float(&x.next().unwrap().bytes())?) } else { None };
+                let coordinates = if options.with_coord { Some(Coordinates::from_response(x.next().unwrap().list())?) } else { None };
+                Ok(GeoMatch { member, distance, coordinates })
+            },
+            _ => unreachable!()
+        }).collect()
+    }
+
+    /// store the members found by `search` into `dest`, with distances as scores if `store_dist` is set,
+    /// or otherwise with geo hashes so `dest` is also a valid Geo. Return the number of stored members.
+    pub fn search_store(&self, center: GeoCenter<'_, T>, shape: GeoShape, options: &GeoSearchOptions, dest: &impl Collection, store_dist: bool) -> Result<usize, RedisError> {
+        let mut sess = self.client.arg(b"geosearchstore");
+        sess.arg(dest.key()).arg(self.key.borrow());
+        self.search_args(&mut sess, center, shape);
+        options.write_args(&mut sess);
+        if store_dist {
+            sess.arg(b"storedist");
+        }
+        sess.fetch().map(|x| x.integer() as _)
+    }
+}
+
+impl<A, C, K: Borrow<[u8]>, T> Collection for Geo<A, C, K, T> {
+    fn key(&self) -> &[u8] {
+        self.key.borrow()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub longitude: f64,
+    pub latitude: f64
+}
+
+impl Coordinates {
+    pub fn new(longitude: f64, latitude: f64) -> Self {
+        Self { longitude, latitude }
+    }
+
+    fn from_response(x: Vec<Response>) -> Result<Self, RedisError> {
+        match &x[..] {
+            [Response::Bytes(longitude), Response::Bytes(latitude)] => Ok(Self::new(parse_float(longitude)?, parse_float(latitude)?)),
+            _ => Err(RedisError::ProtocolError("parse coordinates failed"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit { Meters, Kilometers, Miles, Feet }
+
+impl Unit {
+    fn name(self) -> &'static [u8] {
+        match self {
+            Unit::Meters => b"m",
+            Unit::Kilometers => b"km",
+            Unit::Miles => b"mi",
+            Unit::Feet => b"ft"
+        }
+    }
+}
+
+/// The center of a search, either an existing member or a point.
+pub enum GeoCenter<'t, T> {
+    Member(&'t T),
+    Point(Coordinates)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64, Unit),
+    /// width and height
+    Box(f64, f64, Unit)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder { Asc, Desc }
+
+#[derive(Debug, Clone, Default)]
+pub struct GeoSearchOptions {
+    /// return the distance to the center. Ignored by `search_store`.
+    pub with_dist: bool,
+    /// return the coordinates of members. Ignored by `search_store`.
+    pub with_coord: bool,
+    /// limit the number of results
+    pub count: Option<usize>,
+    /// with `count`, return as soon as enough matches are found instead of the closest ones
+    pub any: bool,
+    /// sort by distance to the center
+    pub order: Option<SortOrder>
+}
+
+impl GeoSearchOptions {
+    fn write_args<T: Read + Write, P: DerefMut<Target=T>>(&self, sess: &mut Session<P>) {
+        if let Some(order) = self.order {
+            sess.arg(match order {
+                SortOrder::Asc => "asc",
+                SortOrder::Desc => "desc"
+            });
+        }
+        if let Some(count) = self.count {
+            sess.arg(b"count").arg(count);
+            if self.any {
+                sess.arg(b"any");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch<T> {
+    pub member: T,
+    pub distance: Option<f64>,
+    pub coordinates: Option<Coordinates>
+}
@@ -33,6 +33,51 @@ pub use list::*;
 mod map;
 pub use map::*;
 
+mod hyperloglog;
+pub use hyperloglog::*;
+
+mod geo;
+pub use geo::*;
+
+mod script;
+pub use script::*;
+
+mod function;
+pub use function::*;
+
+mod lock;
+pub use lock::*;
+
+mod ratelimit;
+pub use ratelimit::*;
+
+mod workqueue;
+pub use workqueue::*;
+
+mod delayed;
+pub use delayed::*;
+
+mod cache;
+pub use cache::*;
+
+mod tracking;
+pub use tracking::*;
+
+mod error;
+pub use error::*;
+
+mod arg;
+pub use arg::*;
+
+mod macros;
+
+mod parser;
+pub use parser::*;
+
+pub mod server;
+
+pub mod testing;
+
 use std::os::unix::net::UnixStream;
 use std::net::TcpStream;
 use std::io::prelude::*;
@@ -40,7 +85,7 @@ use std::sync::*;
 use std::sync::mpsc::{channel, Sender, Receiver};
 use std::ops::{DerefMut, Deref};
 use std::cell::{RefCell, RefMut};
-use detached_bufreader::BufReader;
+use bytes::Bytes;
 use oh_my_rust::*;
 use crate::RedisError::IOError;
 use std::io::Error;
@@ -57,7 +102,7 @@ pub trait AsRedis: Sized {
 
     /// convenient method, create a new session and set an arg
     /// TODO: move this method to another trait? Since we impl AsRedis for many common types including `&mut impl Read + Write`
-    fn arg(self, x: &[u8]) -> Session<Self::P> {
+    fn arg(self, x: impl ToArg) -> Session<Self::P> {
         Session::new(self.as_redis()).apply(|s| s.arg(x).ignore())
     }
 }
@@ -78,6 +123,20 @@ impl<'a, T: Read + Write + 'a> AsRedis for &'a RefCell<T> {
     }
 }
 
+/// Anything that represents the data behind a redis key.
+pub trait Collection {
+    fn key(&self) -> &[u8];
+}
+
+// a bare key, for commands that need a Collection
+struct RawKey<'k>(&'k [u8]);
+
+impl Collection for RawKey<'_> {
+    fn key(&self) -> &[u8] {
+        self.0
+    }
+}
+
 pub struct TcpClient<Addr: std::net::ToSocketAddrs> {
     addr: Addr
 }
@@ -188,22 +247,42 @@ impl<'p, T: Read + Write, P: DerefMut::<Target=T>> AsRedis for &'p Pool<P> {
     }
 }
 
-pub struct Session<P> {
+/// Bytes received after the last response of a Session are lost when it is dropped, as the next Session of the same
+/// connection starts with an empty parser. So `recv` reports them as an error, and dropping a Session reads the
+/// responses of the commands it sent but did not receive, unless the connection is broken or the thread is panicking.
+/// This blocks until those responses arrive, so `send` a blocking command like `BLPOP` only if its response is
+/// received, or drop the connection along with the Session.
+pub struct Session<P: DerefMut> where P::Target: Read + Write + Sized {
     count: usize,
     buf: Vec<u8>,
-    conn: P
+    queued: Vec<u8>, // commands to be written together by `send`
+    conn: P,
+    parser: RespParser,
+    pending: std::collections::VecDeque<Pending> // responses to be received
+}
+
+enum Pending {
+    Reply,
+    /// a command streamed by `send_from`, wrapped in MULTI and EXEC
+    Exec,
+    /// a command aborted by `send_from`, wrapped in MULTI and DISCARD. The replies are skipped.
+    Discard
 }
 
 impl<T: Read + Write, P: std::ops::DerefMut<Target=T>> Session<P> {
     pub fn new(conn: P) -> Self {
-        Self { count: 0, buf: vec![], conn }
+        Self { count: 0, buf: vec![], queued: vec![], conn, parser: RespParser::default(), pending: Default::default() }
     }
 
-    pub fn arg(&mut self, x: &[u8]) -> &mut Self {
-        self.count += 1;
-        write!(self.buf, "${}\r\n", x.len()).expect("bug");
-        self.buf.extend_from_slice(x);
-        self.buf.extend_from_slice(b"\r\n");
+    /// limit the size of replies read in this session
+    pub fn limits(&mut self, limits: RespLimits) -> &mut Self {
+        self.parser.set_limits(limits);
+        self
+    }
+
+    /// add arguments. A single `x` may expand to zero or many arguments, see `ToArg`.
+    pub fn arg(&mut self, x: impl ToArg) -> &mut Self {
+        self.count += x.write_args(&mut self.buf);
         self // for chaining
     }
 
@@ -219,83 +298,244 @@ impl<T: Read + Write, P: std::ops::DerefMut<Target=T>> Session<P> {
         Ok(self)
     }
 
-    /// low level instruction that only send the command without reading response. Note it also clears the buffer.
-    pub fn send(&mut self) -> Result<(), std::io::Error> {
-        write!(self.conn, "*{}\r\n", self.count)?;
-        self.conn.write_all(&self.buf)?;
+    /// finish the command without sending it, so the next command can be built. Queued commands are written
+    /// together by the next `send`.
+    pub fn queue(&mut self) -> &mut Self {
+        write!(self.queued, "*{}\r\n", self.count).expect("bug");
+        self.queued.extend_from_slice(&self.buf);
         self.clear();
-        Ok(())
+        self.pending.push_back(Pending::Reply);
+        self
     }
 
-    /// low level instruction that only read a response without sending request.
+    /// low level instruction that only send the command, after the queued ones, without reading response. Note it
+    /// also clears the buffer. If the write fails the connection is broken, and no response is expected anymore.
+    /// Responses that are not received with `recv` are read when the Session is dropped, which waits for them.
+    pub fn send(&mut self) -> Result<(), std::io::Error> {
+        if self.count != 0 || self.queued.is_empty() {
+            self.queue();
+        }
+        let res = self.conn.write_all(&self.queued);
+        self.queued.clear();
+        if res.is_err() {
+            self.pending.clear()
+        }
+        res
+    }
+
+    /// low level instruction that only read a response without sending request. Bytes received after the response
+    /// are kept for the next `recv`, so pipelined commands can be sent together and their responses read one by one.
     pub fn recv(&mut self) -> Result<Response, RedisError> {
-        let mut reader = BufReader::with_capacity(64, &mut *self.conn);
-        let res = parse_resp(&mut reader);
-        if !reader.buffer().is_empty() {
-            return Err(RedisError::ProtocolError("extra content in response"))
+        self.skip_discarded()?;
+        let res = match self.pending.front() {
+            Some(Pending::Exec) => self.read_exec(),
+            _ => self.parser.read_reply(&mut *self.conn)
+        };
+        self.received(res)
+    }
+
+    // read the replies of MULTI, the command and EXEC or DISCARD. Stop at connection errors.
+    fn read_wrapped(&mut self) -> Result<Vec<Result<Response, RedisError>>, RedisError> {
+        let mut replies = Vec::with_capacity(3);
+        for _ in 0..3 {
+            match self.parser.read_reply(&mut *self.conn) {
+                Err(e) if e.is_connection_error() => return Err(e),
+                x => replies.push(x)
+            }
+        }
+        Ok(replies)
+    }
+
+    fn read_exec(&mut self) -> Result<Response, RedisError> {
+        let mut replies = self.read_wrapped()?;
+        let exec = replies.pop().unwrap();
+        for x in replies {
+            x?.ignore(); // an error when queueing the command is more useful than the EXECABORT of EXEC
+        }
+        match exec? {
+            Response::List(mut x) if x.len() == 1 => Ok(x.pop().unwrap()),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+
+    fn skip_discarded(&mut self) -> Result<(), RedisError> {
+        while let Some(Pending::Discard) = self.pending.front() {
+            if let Err(e) = self.read_wrapped() {
+                self.pending.clear();
+                return Err(e)
+            }
+            self.pending.pop_front();
+        }
+        Ok(())
+    }
+
+    // after all responses of the sent commands are received, nothing else should have been sent
+    fn received<R>(&mut self, res: Result<R, RedisError>) -> Result<R, RedisError> {
+        if let Err(e) = &res {
+            if e.is_connection_error() {
+                self.pending.clear();
+                return res
+            }
+        }
+        match self.pending.pop_front() {
+            None => res, // e.g. messages of subscriptions
+            Some(_) => {
+                if self.pending.is_empty() && self.parser.buffered() != 0 {
+                    return Err(RedisError::ProtocolError("extra content in response"))
+                }
+                res
+            }
+        }
+    }
+
+    /// read `n` responses, e.g. after sending `n` commands. An error response does not stop the reading, so the
+    /// connection stays usable. A connection error does, so fewer than `n` results are returned and the last is the error.
+    pub fn recv_many(&mut self, n: usize) -> Vec<Result<Response, RedisError>> {
+        let mut res = Vec::with_capacity(n);
+        for _ in 0..n {
+            let x = self.recv();
+            let broken = matches!(&x, Err(e) if e.is_connection_error());
+            res.push(x);
+            if broken {
+                break
+            }
         }
         res
     }
 
+    /// execute the command and stream the bulk string response to `w`. Return its length, or None if it is nil.
+    pub fn fetch_into(&mut self, w: &mut impl Write) -> Result<Option<usize>, RedisError> {
+        self.send()?;
+        self.recv_into(w)
+    }
+
+    /// like `recv`, but stream the bulk string response to `w` without holding it in memory. If `w` fails, the whole
+    /// response is still read, so the connection stays usable.
+    pub fn recv_into(&mut self, w: &mut impl Write) -> Result<Option<usize>, RedisError> {
+        self.skip_discarded()?;
+        let res = match self.pending.front() {
+            Some(Pending::Exec) => self.read_exec().and_then(|x| match x {
+                Response::Bytes(x) => Ok(w.write_all(&x).map(|_| Some(x.len()))),
+                Response::Nothing => Ok(Ok(None)),
+                _ => Err(RedisError::ProtocolError("unexpected response type"))
+            }),
+            _ => self.parser.read_bulk_into(&mut *self.conn, w)
+        };
+        // errors of `w` are returned after the connection is known to be in sync
+        self.received(res)?.map_err(RedisError::from)
+    }
+
+    /// like `send`, but the last argument is streamed from `r`, which must provide exactly `len` bytes. The command is
+    /// sent between MULTI and EXEC, so it can not be used within a transaction. If `r` fails or ends early, the rest
+    /// of the payload is padded with zeros and the command is discarded instead of executed, so the connection stays
+    /// usable. `recv` returns the reply of the command itself.
+    pub fn send_from(&mut self, r: &mut impl Read, len: u64) -> Result<(), std::io::Error> {
+        let mut head = std::mem::take(&mut self.queued);
+        write!(head, "*1\r\n$5\r\nmulti\r\n*{}\r\n", self.count + 1).expect("bug");
+        head.extend_from_slice(&self.buf);
+        write!(head, "${}\r\n", len).expect("bug");
+        self.clear();
+        self.conn.write_all(&head)?;
+
+        let mut chunk = vec![0; len.min(64 << 10) as usize];
+        let mut remaining = len;
+        let error = loop {
+            if remaining == 0 {
+                break None
+            }
+            let n = remaining.min(chunk.len() as _) as usize;
+            match r.read(&mut chunk[..n]) {
+                Ok(0) => break Some(std::io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    self.conn.write_all(&chunk[..n])?;
+                    remaining -= n as u64
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => break Some(e)
+            }
+        };
+
+        match error {
+            None => {
+                self.conn.write_all(b"\r\n*1\r\n$4\r\nexec\r\n")?;
+                self.pending.push_back(Pending::Exec);
+                Ok(())
+            },
+            Some(e) => {
+                chunk.fill(0);
+                while remaining > 0 {
+                    let n = remaining.min(chunk.len() as _) as usize;
+                    self.conn.write_all(&chunk[..n])?;
+                    remaining -= n as u64
+                }
+                self.conn.write_all(b"\r\n*1\r\n$7\r\ndiscard\r\n")?;
+                self.pending.push_back(Pending::Discard);
+                Err(e)
+            }
+        }
+    }
+
     fn clear(&mut self) {
         self.count = 0;
         self.buf.clear();
     }
 }
 
-#[derive(Debug)]
-pub enum RedisError {
-    /// RESP protocol error
-    ProtocolError(&'static str),
-    /// Error returned by Redis
-    RedisError(String),
-    /// IO Error in communication with Redis
-    IOError(std::io::Error),
-    /// Errors that you are unlikely to handle by code
-    OtherError(String)
-}
-
-impl From<std::io::Error> for RedisError {
-    fn from(e: Error) -> Self {
-        Self::IOError(e)
+impl<P: DerefMut> Drop for Session<P> where P::Target: Read + Write + Sized {
+    fn drop(&mut self) {
+        // keep the connection in sync for the next Session. Connection errors clear the pending replies.
+        // when unwinding, don't wait for responses that may never come. A connection that outlives the panic is left
+        // out of sync, like one whose Session was leaked.
+        if std::thread::panicking() {
+            return
+        }
+        while !self.pending.is_empty() {
+            if self.skip_discarded().is_err() || self.pending.is_empty() {
+                return
+            }
+            let _ = self.recv();
+        }
     }
 }
 
-fn parse_resp(r: &mut impl BufRead) -> Result<Response, RedisError> {
-    let mut header = String::new();
-    r.read_line(&mut header)?;
-
-    let magic = header.as_bytes()[0];
-    let header = header[1..].trim_end();
+fn parse_float(x: &[u8]) -> Result<f64, RedisError> {
+    std::str::from_utf8(x).ok().and_then(|x| x.parse().ok()).msg(RedisError::ProtocolError("parse float response failed"))
+}
 
-    match magic {
-        b'+' => Ok(Response::Text(header.to_string())),
-        b'-' => Err(RedisError::RedisError(format!("redis error: {}", header))),
-        b':' => Ok(Response::Integer(header.parse().msg(RedisError::ProtocolError("parse integer response failed"))?)),
-        b'$' => if header.as_bytes()[0] == b'-' {
-            Ok(Response::Nothing)
-        } else {
-            let len: u64 = header.parse().msg(RedisError::ProtocolError("parse bytes length failed"))?;
-            let mut buf = r.read_exact_alloc((len + 2) as usize)?;
-            buf.truncate(len as usize); // remove the new line terminator
-            Ok(Response::Bytes(buf.into_boxed_slice()))
+// the length of the first complete RESP value in `buf`, or None if it is incomplete
+fn resp_len(buf: &[u8]) -> Option<usize> {
+    let line = buf.windows(2).position(|x| x == b"\r\n")? + 2;
+    match buf[0] {
+        b'$' => match std::str::from_utf8(&buf[1..line - 2]).ok()?.parse::<i64>().ok()? {
+            x if x < 0 => Some(line),
+            x => Some(line + x as usize + 2).filter(|&len| len <= buf.len())
         },
-        b'*' => if header.as_bytes()[0] == b'-' {
-            Ok(Response::Nothing)
-        } else {
-            let len: u32 = header.parse().msg(RedisError::ProtocolError("parse array length failed"))?;
-            Ok(Response::List((0..len).map(|_| {
-                parse_resp(r)
-            }).collect::<Result<Vec<_>, _>>()?))
+        b'*' => match std::str::from_utf8(&buf[1..line - 2]).ok()?.parse::<i64>().ok()? {
+            x if x < 0 => Some(line),
+            x => (0..x).try_fold(line, |len, _| Some(len + resp_len(&buf[len..])?))
         },
-        _ => Err(RedisError::ProtocolError("unknown response type"))
+        _ => Some(line)
     }
 }
 
+// the length of the first complete command in `buf` and the ranges of its arguments
+fn parse_command(buf: &[u8]) -> Option<(usize, Vec<(usize, usize)>)> {
+    let len = resp_len(buf)?;
+    let mut args = vec![];
+    let mut i = buf.windows(2).position(|x| x == b"\r\n")? + 2;
+    while i < len {
+        let line = buf[i..].windows(2).position(|x| x == b"\r\n")? + 2;
+        let n: usize = std::str::from_utf8(&buf[i + 1..i + line - 2]).ok()?.parse().ok()?;
+        args.push((i + line, i + line + n));
+        i += line + n + 2;
+    }
+    Some((len, args))
+}
+
 #[must_use]
 #[derive(Debug, Clone)]
 pub enum Response {
-    Integer(i64), Text(String), Bytes(Box<[u8]>), List(Vec<Response>), Nothing
+    Integer(i64), Text(String), Bytes(Bytes), List(Vec<Response>), Nothing
 }
 
 impl Response {
@@ -331,7 +571,7 @@ impl Response {
         }
     }
 
-    pub fn bytes(self) -> Box<[u8]> {
+    pub fn bytes(self) -> Bytes {
         if let Response::Bytes(x) = self {
             x
         } else {
@@ -374,4 +614,215 @@ impl Response {
     pub fn is_ok(self) {
         assert!(self.text() == "OK")
     }
+
+    /// encode in RESP2, the inverse of parsing
+    pub fn write_resp(&self, w: &mut impl Write) -> std::io::Result<()> {
+        self.write(w, false)
+    }
+
+    /// encode in RESP3, where nil has its own type
+    pub fn write_resp3(&self, w: &mut impl Write) -> std::io::Result<()> {
+        self.write(w, true)
+    }
+
+    fn write(&self, w: &mut impl Write, resp3: bool) -> std::io::Result<()> {
+        match self {
+            Response::Integer(x) => write!(w, ":{}\r\n", x),
+            Response::Text(x) => write!(w, "+{}\r\n", x),
+            Response::Bytes(x) => {
+                write!(w, "${}\r\n", x.len())?;
+                w.write_all(x)?;
+                w.write_all(b"\r\n")
+            },
+            Response::List(x) => {
+                write!(w, "*{}\r\n", x.len())?;
+                x.iter().try_for_each(|x| x.write(w, resp3))
+            },
+            Response::Nothing => w.write_all(if resp3 { b"_\r\n" } else { b"$-1\r\n" })
+        }
+    }
+
+    // redis-cli style, where nested lists are indented under their index
+    fn format_tty(&self, out: &mut String, prefix: &str) {
+        use std::fmt::Write;
+        match self {
+            Response::List(x) if !x.is_empty() => {
+                let width = x.len().to_string().len();
+                let nested = format!("{}{}", prefix, " ".repeat(width + 2));
+                for (i, x) in x.iter().enumerate() {
+                    write!(out, "{}{:>width$}) ", if i == 0 { "" } else { prefix }, i + 1, width = width).expect("bug");
+                    x.format_tty(out, &nested)
+                }
+            },
+            x => writeln!(out, "{}", Inline(x)).expect("bug")
+        }
+    }
+}
+
+/// Formats like redis-cli, e.g. `1) "a"\n2) (integer) 1`. The alternate form `{:#}` prints on one line, e.g. `["a", (integer) 1]`.
+impl std::fmt::Display for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return write!(f, "{}", Inline(self))
+        }
+        let mut out = String::new();
+        self.format_tty(&mut out, "");
+        f.write_str(out.trim_end_matches('\n'))
+    }
+}
+
+struct Inline<'r>(&'r Response);
+
+impl std::fmt::Display for Inline<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Response::Integer(x) => write!(f, "(integer) {}", x),
+            Response::Text(x) => f.write_str(x),
+            Response::Bytes(x) => {
+                f.write_str("\"")?;
+                for &c in x.iter() {
+                    match c {
+                        b'\\' | b'"' => write!(f, "\\{}", c as char),
+                        b'\n' => f.write_str("\\n"),
+                        b'\r' => f.write_str("\\r"),
+                        b'\t' => f.write_str("\\t"),
+                        7 => f.write_str("\\a"),
+                        8 => f.write_str("\\b"),
+                        c if c.is_ascii_graphic() || c == b' ' => write!(f, "{}", c as char),
+                        c => write!(f, "\\x{:02x}", c)
+                    }?
+                }
+                f.write_str("\"")
+            },
+            Response::List(x) if x.is_empty() => f.write_str("(empty array)"),
+            Response::List(x) => {
+                f.write_str("[")?;
+                for (i, x) in x.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { "" } else { ", " }, Inline(x))?
+                }
+                f.write_str("]")
+            },
+            Response::Nothing => f.write_str("(nil)")
+        }
+    }
+}
+
+/// Types that can be converted from a Response, for commands whose reply type is not fixed, e.g. scripts.
+pub trait FromResponse: Sized {
+    fn from_response(x: Response) -> Result<Self, RedisError>;
+}
+
+impl FromResponse for Response {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        Ok(x)
+    }
+}
+
+impl FromResponse for () {
+    fn from_response(_x: Response) -> Result<Self, RedisError> {
+        Ok(())
+    }
+}
+
+impl FromResponse for i64 {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Integer(x) => Ok(x),
+            Response::Text(x) => x.parse().msg(RedisError::ProtocolError("parse integer response failed")),
+            Response::Bytes(x) => std::str::from_utf8(&x).ok().and_then(|x| x.parse().ok()).msg(RedisError::ProtocolError("parse integer response failed")),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+/// Lua `true` is converted to 1 and `false` to nil
+impl FromResponse for bool {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Integer(x) => Ok(x != 0),
+            Response::Nothing => Ok(false),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+impl FromResponse for String {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Text(x) => Ok(x),
+            Response::Bytes(x) => String::from_utf8(x.to_vec()).msg(RedisError::ProtocolError("invalid utf-8 response")),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+impl FromResponse for Vec<u8> {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Text(x) => Ok(x.into_bytes()),
+            Response::Bytes(x) => Ok(x.to_vec()),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+impl<T: FromResponse> FromResponse for Option<T> {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Nothing => Ok(None),
+            x => T::from_response(x).map(Some)
+        }
+    }
+}
+
+impl<T: FromResponse> FromResponse for Vec<T> {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::List(x) => x.into_iter().map(T::from_response).collect(),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+impl FromResponse for Bytes {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Text(x) => Ok(x.into()),
+            Response::Bytes(x) => Ok(x),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+impl FromResponse for usize {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Integer(x) if x >= 0 => Ok(x as _),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+impl FromResponse for f64 {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::Integer(x) => Ok(x as _),
+            Response::Text(x) => parse_float(x.as_bytes()),
+            Response::Bytes(x) => parse_float(&x),
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
+}
+
+/// from a list of two elements
+impl<A: FromResponse, B: FromResponse> FromResponse for (A, B) {
+    fn from_response(x: Response) -> Result<Self, RedisError> {
+        match x {
+            Response::List(x) if x.len() == 2 => {
+                let mut x = x.into_iter();
+                Ok((A::from_response(x.next().unwrap())?, B::from_response(x.next().unwrap())?))
+            },
+            _ => Err(RedisError::ProtocolError("unexpected response type"))
+        }
+    }
 }
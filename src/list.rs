@@ -27,7 +27,7 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> List<A, C, K, T> where for<'a> &
     }
 
     pub fn push(&self, x: impl Borrow<T>) -> Result<(), RedisError> {
-        self.initiate(b"rpush").arg(&(self.serializer)(x.borrow())).fetch().map(|x| x.ignore())
+        self.initiate(b"rpush").arg((self.serializer)(x.borrow())).fetch().map(|x| x.ignore())
     }
 
     pub fn extend(&self, x: &[impl Borrow<T>]) -> Result<(), RedisError> { // TODO: push in batch if the number is too big
@@ -37,13 +37,13 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> List<A, C, K, T> where for<'a> &
 
         let mut sess = self.initiate(b"rpush");
         for v in x {
-            sess.arg(&(self.serializer)(v.borrow()));
+            sess.arg((self.serializer)(v.borrow()));
         }
         sess.fetch().map(|x| x.ignore())
     }
 
     pub fn push_front(&self, x: impl Borrow<T>) -> Result<(), RedisError> {
-        self.initiate(b"lpush").arg(&(self.serializer)(x.borrow())).fetch().map(|x| x.ignore())
+        self.initiate(b"lpush").arg((self.serializer)(x.borrow())).fetch().map(|x| x.ignore())
     }
 
     pub fn pop(&self) -> Result<Option<T>, RedisError> {
@@ -64,7 +64,7 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> List<A, C, K, T> where for<'a> &
 
     /// blocking pop_front. return None when timeout reached. timeout is the number of seconds to wait. 0 means waiting indefinitely.
     pub fn recv(&self, timeout: i64) -> Result<Option<T>, RedisError> {
-        match self.initiate(b"blpop").arg(timeout.to_string().as_bytes()).fetch()? {
+        match self.initiate(b"blpop").arg(timeout).fetch()? {
             Response::List(x) => Ok(Some((self.deserializer)(x[1].as_bytes()))), // x[0] is the key since `blpop` supports polling multiple keys
             Response::Nothing => Ok(None),
             _ => unreachable!()
@@ -72,7 +72,7 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> List<A, C, K, T> where for<'a> &
     }
 
     pub fn get(&self, i: i64) -> Result<Option<T>, RedisError> {
-        match self.initiate(b"lindex").arg(i.to_string().as_bytes()).fetch()? {
+        match self.initiate(b"lindex").arg(i).fetch()? {
             Response::Bytes(x) => Ok(Some((self.deserializer)(&x))),
             Response::Nothing => Ok(None),
             _ => unreachable!()
@@ -81,7 +81,7 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> List<A, C, K, T> where for<'a> &
 
     /// Sets the list element at i to v. An error is returned for out of range indexes.
     pub fn set(&self, i: i64, v: impl Borrow<T>) -> Result<(), RedisError> {
-        self.initiate(b"lset").arg(i.to_string().as_bytes()).arg(&(self.serializer)(v.borrow())).fetch().map(|x| x.ignore())
+        self.initiate(b"lset").arg(i).arg((self.serializer)(v.borrow())).fetch().map(|x| x.ignore())
     }
 
     pub fn iter(&self) -> impl Iterator<Item=T> + '_ {
@@ -122,7 +122,7 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> List<A, C, K, T> where for<'a> &
                 x + 1
             },
             Bound::Unbounded => 0
-        } as i64;
+        };
 
         let end = match range.end_bound() {
             Bound::Included(x) => *x,
@@ -135,14 +135,20 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> List<A, C, K, T> where for<'a> &
         };
 
         Ok(self.initiate(b"lrange")
-            .arg(start.to_string().as_bytes())
-            .arg(end.to_string().as_bytes())
+            .arg(start)
+            .arg(end)
             .fetch()?.list().into_iter()
             .map(|x| (self.deserializer)(&x.bytes()))
             .collect())
     }
 }
 
+impl<A, C, K: Borrow<[u8]>, T> Collection for List<A, C, K, T> {
+    fn key(&self) -> &[u8] {
+        self.key.borrow()
+    }
+}
+
 const BATCH_SIZE: usize = 12;
 
 pub struct ListIter<'l, A, C, K, T> {
@@ -157,8 +163,8 @@ impl<'l, A, C: Deref<Target=A>, K: Borrow<[u8]>, T> Iterator for ListIter<'l, A,
     fn next(&mut self) -> Option<Self::Item> {
         if self.buf.is_empty() { // try to get a batch
             let batch = self.list.initiate(b"lrange")
-                .arg(self.index.to_string().as_bytes())
-                .arg((self.index + BATCH_SIZE).to_string().as_bytes())
+                .arg(self.index)
+                .arg(self.index + BATCH_SIZE)
                 .fetch().expect("Error during iteration").list();
 
             self.index += batch.len();
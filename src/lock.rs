@@ -0,0 +1,172 @@
+use crate::*;
+use std::borrow::Borrow;
+use std::time::{Duration, Instant};
+use std::hash::{BuildHasher, Hasher};
+use std::collections::hash_map::RandomState;
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Lock is a mutex shared by all clients of the same key. The lock expires after a ttl in case the holder crashed.
+/// It is a `Cell` holding a random token of the holder, which is only cleared or extended while it still matches.
+pub struct Lock<A, C, K>
+{
+    cell: Cell<A, C, K, String>
+}
+
+impl<A, C: Deref<Target=A>, K: Borrow<[u8]>> Lock<A, C, K> where for<'a> &'a A: AsRedis {
+    pub fn new(client: C, key: K) -> Self {
+        Self { cell: token_cell(client, key) }
+    }
+
+    /// try to acquire the lock once. Return None if it is held by others. A ttl below 1ms is an error.
+    pub fn try_acquire(&self, ttl: Duration) -> Result<Option<LockGuard<'_, A, C, K>>, RedisError> {
+        let token = new_token();
+        Ok(if self.cell.set_if_absent(&token, Some(ttl))? {
+            Some(LockGuard { lock: self, token })
+        } else {
+            None
+        })
+    }
+
+    /// keep trying to acquire the lock for at most `wait`. Return None if timeout reached.
+    pub fn acquire(&self, ttl: Duration, wait: Duration) -> Result<Option<LockGuard<'_, A, C, K>>, RedisError> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if let Some(guard) = self.try_acquire(ttl)? {
+                return Ok(Some(guard))
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None)
+            }
+            std::thread::sleep(RETRY_INTERVAL.min(deadline - now))
+        }
+    }
+}
+
+impl<A, C, K: Borrow<[u8]>> Collection for Lock<A, C, K> {
+    fn key(&self) -> &[u8] {
+        self.cell.key()
+    }
+}
+
+/// The lock is released when the guard is dropped, unless it already expired and was acquired by others.
+pub struct LockGuard<'l, A, C: Deref<Target=A>, K: Borrow<[u8]>> where for<'a> &'a A: AsRedis {
+    lock: &'l Lock<A, C, K>,
+    token: String
+}
+
+impl<'l, A, C: Deref<Target=A>, K: Borrow<[u8]>> LockGuard<'l, A, C, K> where for<'a> &'a A: AsRedis {
+    /// reset the ttl of the lock. Return false if the lock is already lost.
+    pub fn extend(&self, ttl: Duration) -> Result<bool, RedisError> {
+        self.lock.cell.expire_if_eq(&self.token, ttl)
+    }
+
+    /// release the lock and report whether it was still held. Dropping the guard does the same but ignores the result.
+    pub fn release(self) -> Result<bool, RedisError> {
+        let res = self.lock.cell.remove_if_eq(&self.token);
+        std::mem::forget(self);
+        res
+    }
+}
+
+impl<'l, A, C: Deref<Target=A>, K: Borrow<[u8]>> Drop for LockGuard<'l, A, C, K> where for<'a> &'a A: AsRedis {
+    fn drop(&mut self) {
+        self.lock.cell.remove_if_eq(&self.token).ignore()
+    }
+}
+
+/// Redlock is a Lock over several independent Redis instances. It is held when a majority of the instances are locked,
+/// so it survives the failure of a minority of them. Errors of single instances are treated as failing to lock them.
+pub struct Redlock<A, C, K>
+{
+    cells: Vec<Cell<A, C, K, String>>
+}
+
+impl<A, C: Deref<Target=A>, K: Borrow<[u8]>> Redlock<A, C, K> where for<'a> &'a A: AsRedis {
+    pub fn new(clients: Vec<C>, key: K) -> Self where K: Clone {
+        Self { cells: clients.into_iter().map(|client| token_cell(client, key.clone())).collect() }
+    }
+
+    /// try to acquire the lock once. The guard is valid for `ttl` minus the time spent acquiring it and a clock drift allowance.
+    pub fn try_acquire(&self, ttl: Duration) -> Option<RedlockGuard<'_, A, C, K>> {
+        let token = new_token();
+        let start = Instant::now();
+        let locked = self.cells.iter()
+            .filter(|cell| cell.set_if_absent(&token, Some(ttl)).unwrap_or(false))
+            .count();
+
+        let drift = ttl / 100 + Duration::from_millis(2);
+        let guard = RedlockGuard { lock: self, token, valid_until: start + ttl.checked_sub(drift).unwrap_or_default() };
+        if locked > self.cells.len() / 2 && Instant::now() < guard.valid_until {
+            Some(guard)
+        } else {
+            None // the guard unlocks the instances that we did lock
+        }
+    }
+
+    /// keep trying to acquire the lock for at most `wait`. Return None if timeout reached.
+    pub fn acquire(&self, ttl: Duration, wait: Duration) -> Option<RedlockGuard<'_, A, C, K>> {
+        let deadline = Instant::now() + wait;
+        loop {
+            if let Some(guard) = self.try_acquire(ttl) {
+                return Some(guard)
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return None
+            }
+            std::thread::sleep(RETRY_INTERVAL.min(deadline - now))
+        }
+    }
+}
+
+pub struct RedlockGuard<'l, A, C: Deref<Target=A>, K: Borrow<[u8]>> where for<'a> &'a A: AsRedis {
+    lock: &'l Redlock<A, C, K>,
+    token: String,
+    valid_until: Instant
+}
+
+impl<'l, A, C: Deref<Target=A>, K: Borrow<[u8]>> RedlockGuard<'l, A, C, K> where for<'a> &'a A: AsRedis {
+    /// the lock may be acquired by others after this instant
+    pub fn valid_until(&self) -> Instant {
+        self.valid_until
+    }
+
+    /// reset the ttl on all instances. Return false if the majority of the instances are lost.
+    pub fn extend(&mut self, ttl: Duration) -> bool {
+        let start = Instant::now();
+        let extended = self.lock.cells.iter()
+            .filter(|cell| cell.expire_if_eq(&self.token, ttl).unwrap_or(false))
+            .count();
+
+        if extended > self.lock.cells.len() / 2 {
+            self.valid_until = start + ttl.checked_sub(ttl / 100 + Duration::from_millis(2)).unwrap_or_default();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<'l, A, C: Deref<Target=A>, K: Borrow<[u8]>> Drop for RedlockGuard<'l, A, C, K> where for<'a> &'a A: AsRedis {
+    fn drop(&mut self) {
+        for cell in &self.lock.cells {
+            cell.remove_if_eq(&self.token).ignore()
+        }
+    }
+}
+
+// unique among processes and threads with high probability, though not cryptographically secure
+pub(crate) fn new_token() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+    format!("{:016x}{:08x}", hasher.finish(), std::process::id())
+}
+
+fn token_cell<A, C: Deref<Target=A>, K: Borrow<[u8]>>(client: C, key: K) -> Cell<A, C, K, String> where for<'a> &'a A: AsRedis {
+    Cell::new(client, key, |x: &String| x.as_bytes().into(), |x| String::from_utf8_lossy(x).into_owned())
+}
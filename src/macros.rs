@@ -0,0 +1,36 @@
+/// Start a command on a client with any arguments implementing `ToArg`, returning a `Session` ready to `fetch`.
+///
+/// ```ignore
+/// let added = cmd!(&client, "zadd", b"scores", 1.5, "alice").fetch()?.integer();
+/// ```
+#[macro_export]
+macro_rules! cmd {
+    ($client:expr, $name:expr $(, $arg:expr)* $(,)?) => {{
+        let mut sess = $crate::AsRedis::arg($client, $name);
+        $(sess.arg($arg);)*
+        sess
+    }};
+}
+
+/// Send several commands on one connection in a single write, then read all the replies.
+/// Evaluates to `Result<Vec<Result<Response, RedisError>>, RedisError>` with a result for each command, see
+/// `Session::recv_many`. The outer error is returned if the commands can not be sent.
+///
+/// ```ignore
+/// let replies = cmd_pipe!(&client, ["set", b"k", 1], ["incrby", b"k", 2], ["get", b"k"])?;
+/// ```
+#[macro_export]
+macro_rules! cmd_pipe {
+    ($client:expr $(, [$name:expr $(, $arg:expr)* $(,)?])+ $(,)?) => {{
+        let mut sess = $crate::Session::new($crate::AsRedis::as_redis($client));
+        let mut n = 0;
+        $(
+            sess.arg($name)$(.arg($arg))*.queue();
+            n += 1;
+        )+
+        match sess.send() {
+            Ok(()) => Ok(sess.recv_many(n)),
+            Err(e) => Err($crate::RedisError::from(e))
+        }
+    }};
+}
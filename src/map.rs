@@ -1,7 +1,9 @@
 use crate::*;
 use std::borrow::Borrow;
-use std::collections::VecDeque;
+use std::collections::{VecDeque, HashMap};
+use std::hash::Hash;
 use std::ops::{RangeBounds, Bound};
+use std::time::Duration;
 
 /// Maps associate fields with values
 pub struct Map<A, C, K, F, V>
@@ -35,7 +37,7 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, F, V> Map<A, C, K, F, V> where for<
     }
 
     pub fn get(&self, field: impl Borrow<F>) -> Result<Option<V>, RedisError> {
-        match self.initiate(b"hget").arg(&(self.field_serializer)(field.borrow())).fetch()? {
+        match self.initiate(b"hget").arg((self.field_serializer)(field.borrow())).fetch()? {
             Response::Bytes(x) => Ok(Some((self.value_deserializer)(&x))),
             Response::Nothing => Ok(None),
             _ => unreachable!()
@@ -44,21 +46,84 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, F, V> Map<A, C, K, F, V> where for<
 
     pub fn insert(&self, field: impl Borrow<F>, value: impl Borrow<V>) -> Result<(), RedisError> {
         self.initiate(b"hset")
-            .arg(&(self.field_serializer)(field.borrow()))
-            .arg(&(self.value_serializer)(value.borrow()))
+            .arg((self.field_serializer)(field.borrow()))
+            .arg((self.value_serializer)(value.borrow()))
             .fetch().map(|x| x.ignore())
     }
 
     pub fn remove(&self, field: impl Borrow<F>) -> Result<(), RedisError> {
-        self.initiate(b"hdel").arg(&(self.field_serializer)(field.borrow())).fetch().map(|x| x.ignore())
+        self.initiate(b"hdel").arg((self.field_serializer)(field.borrow())).fetch().map(|x| x.ignore())
     }
 
     pub fn contains_key(&self, field: impl Borrow<F>) -> Result<bool, RedisError> {
-        self.initiate(b"hexists").arg(&(self.field_serializer)(field.borrow())).fetch().map(|x| x.integer() == 1)
+        self.initiate(b"hexists").arg((self.field_serializer)(field.borrow())).fetch().map(|x| x.integer() == 1)
     }
 
-    pub fn extend(&self, _pairs: &[(F, V)]) -> Result<(), RedisError> {
-        unimplemented!()
+    /// insert the value only if the field does not exist yet. Return whether the value is inserted.
+    pub fn insert_if_absent(&self, field: impl Borrow<F>, value: impl Borrow<V>) -> Result<bool, RedisError> {
+        self.initiate(b"hsetnx")
+            .arg((self.field_serializer)(field.borrow()))
+            .arg((self.value_serializer)(value.borrow()))
+            .fetch().map(|x| x.integer() == 1)
+    }
+
+    /// return the number of fields that were actually removed
+    pub fn remove_many(&self, fields: &[impl Borrow<F>]) -> Result<usize, RedisError> {
+        if fields.is_empty() {
+            return Ok(0)
+        }
+
+        let mut sess = self.initiate(b"hdel");
+        for field in fields {
+            sess.arg((self.field_serializer)(field.borrow()));
+        }
+        sess.fetch().map(|x| x.integer() as _)
+    }
+
+    /// insert multiple pairs. Large inputs are sent in chunks, so the whole operation is not atomic.
+    pub fn extend(&self, pairs: &[(impl Borrow<F>, impl Borrow<V>)]) -> Result<(), RedisError> {
+        for chunk in pairs.chunks(EXTEND_BATCH) {
+            let mut sess = self.initiate(b"hset");
+            for (field, value) in chunk {
+                sess.arg((self.field_serializer)(field.borrow()))
+                    .arg((self.value_serializer)(value.borrow()));
+            }
+            sess.fetch()?.ignore()
+        }
+        Ok(())
+    }
+
+    /// get the values of multiple fields in one round trip. The result is in the same order as `fields`.
+    pub fn get_many(&self, fields: &[impl Borrow<F>]) -> Result<Vec<Option<V>>, RedisError> {
+        if fields.is_empty() {
+            return Ok(vec![])
+        }
+
+        let mut sess = self.initiate(b"hmget");
+        for field in fields {
+            sess.arg((self.field_serializer)(field.borrow()));
+        }
+        Ok(sess.fetch()?.list().into_iter().map(|x| match x {
+            Response::Bytes(x) => Some((self.value_deserializer)(&x)),
+            Response::Nothing => None,
+            _ => unreachable!()
+        }).collect())
+    }
+
+    /// increment the integer stored at `field` by `delta` and return the new value. Missing fields are treated as 0.
+    pub fn increment(&self, field: impl Borrow<F>, delta: i64) -> Result<i64, RedisError> {
+        self.initiate(b"hincrby")
+            .arg((self.field_serializer)(field.borrow()))
+            .arg(delta)
+            .fetch().map(|x| x.integer())
+    }
+
+    /// increment the float stored at `field` by `delta` and return the new value. Missing fields are treated as 0.
+    pub fn increment_float(&self, field: impl Borrow<F>, delta: f64) -> Result<f64, RedisError> {
+        self.initiate(b"hincrbyfloat")
+            .arg((self.field_serializer)(field.borrow()))
+            .arg(delta)
+            .fetch().and_then(|x| parse_float(&x.bytes()))
     }
 
     pub fn len(&self) -> Result<usize, RedisError> {
@@ -72,13 +137,119 @@ impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, F, V> Map<A, C, K, F, V> where for<
     pub fn iter(&self) -> impl Iterator<Item=(F, V)> + '_ {
         self.into_iter()
     }
+
+    // the `fields` family: `<cmd> key [options] fields 1 field`
+    fn initiate_fields(&self, cmd: &[u8], options: &[&dyn ToArg], field: &F) -> Session<<&A as AsRedis>::P> {
+        self.initiate(cmd).apply(|x| x.arg(options).arg(b"fields").arg(1).arg((self.field_serializer)(field)).ignore())
+    }
+
+    /// set a time to live on a single field. Requires Redis 7.4.
+    pub fn expire_field(&self, field: impl Borrow<F>, ttl: Duration) -> Result<FieldStatus, RedisError> {
+        let mut res = self.initiate_fields(b"hpexpire", &[&ttl], field.borrow()).fetch()?.list();
+        match res.pop().unwrap().integer() {
+            -2 => Ok(FieldStatus::NoSuchField),
+            0 => Ok(FieldStatus::Unchanged),
+            1 => Ok(FieldStatus::Updated),
+            2 => Ok(FieldStatus::Deleted),
+            _ => Err(RedisError::ProtocolError("unknown field status"))
+        }
+    }
+
+    /// remove the time to live of a single field. Requires Redis 7.4.
+    pub fn persist_field(&self, field: impl Borrow<F>) -> Result<FieldStatus, RedisError> {
+        let mut res = self.initiate_fields(b"hpersist", &[], field.borrow()).fetch()?.list();
+        match res.pop().unwrap().integer() {
+            -2 => Ok(FieldStatus::NoSuchField),
+            -1 => Ok(FieldStatus::Unchanged),
+            1 => Ok(FieldStatus::Updated),
+            _ => Err(RedisError::ProtocolError("unknown field status"))
+        }
+    }
+
+    /// get the remaining time to live of a single field. Requires Redis 7.4.
+    pub fn field_ttl(&self, field: impl Borrow<F>) -> Result<FieldTtl, RedisError> {
+        let mut res = self.initiate_fields(b"hpttl", &[], field.borrow()).fetch()?.list();
+        match res.pop().unwrap().integer() {
+            -2 => Ok(FieldTtl::NoSuchField),
+            -1 => Ok(FieldTtl::Persistent),
+            x if x >= 0 => Ok(FieldTtl::Expires(Duration::from_millis(x as _))),
+            _ => Err(RedisError::ProtocolError("unknown field ttl"))
+        }
+    }
+
+    /// insert a field that expires after `ttl`. Requires Redis 8.0.
+    pub fn insert_with_ttl(&self, field: impl Borrow<F>, value: impl Borrow<V>, ttl: Duration) -> Result<(), RedisError> {
+        self.initiate_fields(b"hsetex", &[&"px", &ttl], field.borrow())
+            .arg((self.value_serializer)(value.borrow()))
+            .fetch().map(|x| x.ignore())
+    }
+
+    /// get the value of a field and reset its time to live to `ttl`. Requires Redis 8.0.
+    pub fn get_and_expire(&self, field: impl Borrow<F>, ttl: Duration) -> Result<Option<V>, RedisError> {
+        let mut res = self.initiate_fields(b"hgetex", &[&"px", &ttl], field.borrow()).fetch()?.list();
+        match res.pop().unwrap() {
+            Response::Bytes(x) => Ok(Some((self.value_deserializer)(&x))),
+            Response::Nothing => Ok(None),
+            _ => unreachable!()
+        }
+    }
+
+    /// fetch all fields at once with `hkeys`. Use `iter` for large maps.
+    pub fn keys(&self) -> Result<impl Iterator<Item=F> + '_, RedisError> {
+        Ok(self.initiate(b"hkeys").fetch()?.list().into_iter().map(move |x| (self.field_deserializer)(&x.bytes())))
+    }
+
+    /// fetch all values at once with `hvals`. Use `iter` for large maps.
+    pub fn values(&self) -> Result<impl Iterator<Item=V> + '_, RedisError> {
+        Ok(self.initiate(b"hvals").fetch()?.list().into_iter().map(move |x| (self.value_deserializer)(&x.bytes())))
+    }
+
+    /// get the entry of `field` for in-place manipulation, like `HashMap::entry`.
+    pub fn entry(&self, field: impl Borrow<F>) -> Entry<'_, A, C, K, F, V> {
+        Entry { field: (self.field_serializer)(field.borrow()), modifiers: vec![], map: self }
+    }
+
+    /// fetch the whole map at once with `hgetall`.
+    pub fn to_hashmap(&self) -> Result<HashMap<F, V>, RedisError> where F: Hash + Eq {
+        let mut res = HashMap::new();
+        let mut pairs = self.initiate(b"hgetall").fetch()?.list().into_iter();
+        while let (Some(field), Some(value)) = (pairs.next(), pairs.next()) {
+            res.insert((self.field_deserializer)(&field.bytes()), (self.value_deserializer)(&value.bytes()));
+        }
+        Ok(res)
+    }
+}
+
+impl<A, C, K: Borrow<[u8]>, F, V> Collection for Map<A, C, K, F, V> {
+    fn key(&self) -> &[u8] {
+        self.key.borrow()
+    }
+}
+
+/// Per-field status codes returned by the `hexpire` family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldStatus {
+    NoSuchField,
+    /// the condition is not met, or there is no ttl to remove
+    Unchanged,
+    Updated,
+    /// the field is deleted because the given ttl is already expired
+    Deleted
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldTtl {
+    NoSuchField,
+    Persistent,
+    Expires(Duration)
 }
 
 const BATCH_HINT: usize = 12;
+const EXTEND_BATCH: usize = 512;
 
 pub struct MapIter<'m, A, C, K, F, V> {
     buf: VecDeque<(F, V)>,
-    cursor: Box<[u8]>,
+    cursor: Bytes,
     map: &'m Map<A, C, K, F, V>,
     done: bool
 }
@@ -127,6 +298,114 @@ impl<'m, A, C: Deref<Target=A>, K: Borrow<[u8]>, F, V> IntoIterator for &'m Map<
     type IntoIter = MapIter<'m, A, C, K, F, V>;
 
     fn into_iter(self) -> Self::IntoIter {
-        MapIter { buf: VecDeque::with_capacity(BATCH_HINT), cursor: b"0"[..].into(), map: self, done: false }
+        MapIter { buf: VecDeque::with_capacity(BATCH_HINT), cursor: Bytes::from_static(b"0"), map: self, done: false }
+    }
+}
+
+type Modifier<'m, V> = Box<dyn FnMut(&mut V) + 'm>;
+
+/// A view into a single field of a Map. Nothing is sent until one of the `or_*` methods is called,
+/// which performs the whole chain atomically and returns the resulting value.
+pub struct Entry<'m, A, C, K, F, V> {
+    field: Box<[u8]>,
+    modifiers: Vec<Modifier<'m, V>>,
+    map: &'m Map<A, C, K, F, V>
+}
+
+impl<'m, A, C: Deref<Target=A>, K: Borrow<[u8]>, F, V> Entry<'m, A, C, K, F, V> where for<'a> &'a A: AsRedis {
+    /// modify the value if the field exists. The closure may be called multiple times if there are concurrent writers.
+    pub fn and_modify(mut self, f: impl FnMut(&mut V) + 'm) -> Self {
+        self.modifiers.push(Box::new(f));
+        self
+    }
+
+    pub fn or_insert(self, default: V) -> Result<V, RedisError> {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_default(self) -> Result<V, RedisError> where V: Default {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> Result<V, RedisError> {
+        if self.modifiers.is_empty() {
+            self.insert_if_absent(default)
+        } else {
+            self.modify_or_insert(default)
+        }
+    }
+
+    // hsetnx is enough when there is nothing to modify. Loop in case the field is deleted between hsetnx and hget.
+    fn insert_if_absent(self, default: impl FnOnce() -> V) -> Result<V, RedisError> {
+        let map = self.map;
+        let mut sess = Session::new((*map.client).as_redis());
+        let mut default = Some(default);
+        let mut value = None;
+
+        loop {
+            if let Response::Bytes(x) = sess.arg(b"hget").arg(map.key.borrow()).arg(&self.field).fetch()? {
+                return Ok((map.value_deserializer)(&x))
+            }
+
+            let v = value.get_or_insert_with(|| (default.take().unwrap())());
+            let inserted = sess.arg(b"hsetnx").arg(map.key.borrow()).arg(&self.field)
+                .arg((map.value_serializer)(v))
+                .fetch()?.integer() == 1;
+            if inserted {
+                return Ok(value.unwrap())
+            }
+        }
+    }
+
+    // optimistic locking with watch/multi/exec, retry until no one else touched the key
+    fn modify_or_insert(mut self, default: impl FnOnce() -> V) -> Result<V, RedisError> {
+        let map = self.map;
+        let mut sess = Session::new((*map.client).as_redis());
+        let mut default = Some(default);
+        let mut fresh = None; // the default value is kept across retries so the closure is called at most once
+
+        loop {
+            sess.arg(b"watch").arg(map.key.borrow()).run()?;
+            let mut tx = Transaction { sess: &mut sess, cleanup: Some(b"unwatch") };
+            let (value, is_fresh) = match tx.sess.arg(b"hget").arg(map.key.borrow()).arg(&self.field).fetch()? {
+                Response::Bytes(x) => {
+                    let mut v = (map.value_deserializer)(&x);
+                    for f in self.modifiers.iter_mut() {
+                        f(&mut v)
+                    }
+                    (v, false)
+                },
+                Response::Nothing => (fresh.take().unwrap_or_else(|| (default.take().unwrap())()), true),
+                _ => unreachable!()
+            };
+
+            tx.sess.arg(b"multi").run()?;
+            tx.cleanup = Some(b"discard");
+            tx.sess.arg(b"hset").arg(map.key.borrow()).arg(&self.field).arg((map.value_serializer)(&value)).run()?;
+            tx.cleanup = None; // exec ends the transaction even if it fails
+            match tx.sess.arg(b"exec").fetch()? {
+                Response::List(_) => return Ok(value),
+                Response::Nothing => if is_fresh { // aborted by concurrent modification
+                    fresh = Some(value)
+                },
+                _ => unreachable!()
+            }
+        }
+    }
+}
+
+// leaves the watch or multi state on drop, so an error or a panic in the modifiers
+// does not leak it to the next user of a shared connection
+struct Transaction<'s, P: DerefMut> where P::Target: Read + Write + Sized {
+    sess: &'s mut Session<P>,
+    cleanup: Option<&'static [u8]>
+}
+
+impl<P: DerefMut> Drop for Transaction<'_, P> where P::Target: Read + Write + Sized {
+    fn drop(&mut self) {
+        if let Some(cmd) = self.cleanup {
+            self.sess.clear();
+            self.sess.arg(cmd).fetch().ok();
+        }
     }
 }
@@ -0,0 +1,240 @@
+use crate::*;
+use bytes::{Buf, Bytes, BytesMut};
+
+/// Bounds on the replies a parser accepts, so a broken or hostile server cannot make us allocate without limit.
+#[derive(Debug, Clone, Copy)]
+pub struct RespLimits {
+    /// the longest bulk string or status line, in bytes
+    pub max_bulk: usize,
+    /// the most elements in a single array
+    pub max_array: usize
+}
+
+/// The defaults follow the server side `proto-max-bulk-len`.
+impl Default for RespLimits {
+    fn default() -> Self {
+        Self { max_bulk: 512 << 20, max_array: u32::MAX as _ }
+    }
+}
+
+/// An incremental RESP parser. Bytes can be fed in chunks of any size and parsing resumes where it stopped,
+/// so a partially received reply is never scanned twice and the bytes after a reply are kept for the next one.
+/// Bulk strings are split off the read buffer without copying.
+#[derive(Default)]
+pub struct RespParser {
+    buf: BytesMut,
+    limits: RespLimits,
+    bulk: Option<usize>, // length of the bulk string whose header has been consumed
+    stack: Vec<(Vec<Response>, usize)>, // arrays being filled and their lengths
+    error: Option<ServerError> // the first error nested in the current reply
+}
+
+enum Step {
+    Incomplete,
+    Nested,
+    Value(Response)
+}
+
+impl RespParser {
+    pub fn new(limits: RespLimits) -> Self {
+        Self { limits, ..Self::default() }
+    }
+
+    pub fn set_limits(&mut self, limits: RespLimits) {
+        self.limits = limits
+    }
+
+    /// the number of received bytes not yet consumed
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn feed(&mut self, x: &[u8]) {
+        self.buf.extend_from_slice(x)
+    }
+
+    /// read once from `r` into the buffer and return the number of bytes read
+    pub fn read_from(&mut self, r: &mut impl Read) -> std::io::Result<usize> {
+        // the rest of a bulk string is reserved at once, but only a bounded chunk is zeroed for each read, so a large
+        // bulk string arriving in small pieces costs linear time
+        let len = self.buf.len();
+        let remaining = self.bulk.map_or(0, |x| (x + 2).saturating_sub(len));
+        self.buf.reserve(remaining);
+        let chunk = remaining.clamp(4096, 64 << 10);
+        self.buf.resize(len + chunk, 0);
+        let n = r.read(&mut self.buf[len..]);
+        self.buf.truncate(len + *n.as_ref().unwrap_or(&0));
+        n
+    }
+
+    /// the first unconsumed byte, which tells the type of the next value
+    pub fn peek(&self) -> Option<u8> {
+        self.buf.first().copied()
+    }
+
+    /// read a line ending with `\n` or `\r\n`, e.g. an inline command, and return it without the line ending
+    pub fn read_line(&mut self, r: &mut impl Read) -> Result<Bytes, RedisError> {
+        loop {
+            if let Some(end) = self.buf.iter().position(|&x| x == b'\n') {
+                let line = self.buf.split_to(end + 1).freeze();
+                return Ok(line.slice(..if line[..end].ends_with(b"\r") { end - 1 } else { end }))
+            }
+            if self.buf.len() > self.limits.max_bulk {
+                return Err(RedisError::ProtocolError("line too long"))
+            }
+            self.read_exact_more(r)?
+        }
+    }
+
+    /// read a bulk string reply and copy its payload to `w` in chunks, without holding it in memory. Return the length
+    /// of the payload, or None if the reply is nil. Other types of replies are consumed and reported as errors.
+    /// If `w` fails, the rest of the payload is still read and discarded, and the error of `w` is the inner result,
+    /// so unlike the outer errors it leaves the parser in sync with `r`.
+    pub fn read_bulk_into(&mut self, r: &mut impl Read, w: &mut impl Write) -> Result<std::io::Result<Option<usize>>, RedisError> {
+        let end = loop {
+            if !self.stack.is_empty() || matches!(self.buf.first(), Some(&x) if x != b'$') {
+                return match self.read_reply(r)? {
+                    Response::Nothing => Ok(Ok(None)),
+                    _ => Err(RedisError::ProtocolError("unexpected response type"))
+                }
+            }
+            match self.buf.windows(2).position(|x| x == b"\r\n") {
+                Some(x) => break x,
+                None if self.buf.len() > self.limits.max_bulk => return Err(RedisError::ProtocolError("line too long")),
+                None => self.read_exact_more(r)?
+            }
+        };
+        let len: i64 = std::str::from_utf8(&self.buf[1..end]).ok().and_then(|x| x.parse().ok()).msg(RedisError::ProtocolError("parse bytes length failed"))?;
+        self.buf.advance(end + 2);
+        if len < 0 {
+            return Ok(Ok(None))
+        }
+
+        // the payload beyond the buffer is read directly into a chunk, so the buffer never grows with it.
+        // after `w` fails, the payload is only read to keep in sync.
+        let mut remaining = len as usize;
+        let n = remaining.min(self.buf.len());
+        let mut written = w.write_all(&self.buf[..n]);
+        self.buf.advance(n);
+        remaining -= n;
+        let mut chunk = vec![0; remaining.min(64 << 10)];
+        while remaining > 0 {
+            let n = remaining.min(chunk.len());
+            let n = r.read(&mut chunk[..n])?;
+            if n == 0 {
+                return Err(RedisError::IOError(std::io::ErrorKind::UnexpectedEof.into()))
+            }
+            if written.is_ok() {
+                written = w.write_all(&chunk[..n])
+            }
+            remaining -= n
+        }
+
+        while self.buf.len() < 2 {
+            self.read_exact_more(r)?
+        }
+        if &self.buf[..2] != b"\r\n" {
+            return Err(RedisError::ProtocolError("bulk string not terminated"))
+        }
+        self.buf.advance(2);
+        Ok(written.map(|_| Some(len as _)))
+    }
+
+    /// parse a complete reply, reading from `r` as needed
+    pub fn read_reply(&mut self, r: &mut impl Read) -> Result<Response, RedisError> {
+        loop {
+            if let Some(x) = self.parse()? {
+                return Ok(x)
+            }
+            self.read_exact_more(r)?
+        }
+    }
+
+    fn read_exact_more(&mut self, r: &mut impl Read) -> Result<(), RedisError> {
+        match self.read_from(r)? {
+            0 => Err(RedisError::IOError(std::io::ErrorKind::UnexpectedEof.into())),
+            _ => Ok(())
+        }
+    }
+
+    /// parse the next complete reply, or return None if more bytes are needed. Errors nested in an array are
+    /// reported after the whole array is consumed. After a protocol error the connection should be dropped.
+    pub fn parse(&mut self) -> Result<Option<Response>, RedisError> {
+        loop {
+            let mut value = match self.step()? {
+                Step::Incomplete => return Ok(None),
+                Step::Nested => continue,
+                Step::Value(x) => x
+            };
+
+            loop {
+                let (items, len) = match self.stack.last_mut() {
+                    Some(x) => x,
+                    None => return match self.error.take() {
+                        Some(e) => Err(e.into()),
+                        None => Ok(Some(value))
+                    }
+                };
+                items.push(value);
+                if items.len() < *len {
+                    break
+                }
+                value = Response::List(self.stack.pop().unwrap().0)
+            }
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, RedisError> {
+        if let Some(len) = self.bulk {
+            if self.buf.len() < len + 2 {
+                return Ok(Step::Incomplete)
+            }
+            if &self.buf[len..len + 2] != b"\r\n" {
+                return Err(RedisError::ProtocolError("bulk string not terminated"))
+            }
+            let x = self.buf.split_to(len).freeze();
+            self.buf.advance(2);
+            self.bulk = None;
+            return Ok(Step::Value(Response::Bytes(x)))
+        }
+
+        let end = match self.buf.windows(2).position(|x| x == b"\r\n") {
+            Some(0) => return Err(RedisError::ProtocolError("empty line in response")),
+            Some(x) => x,
+            None if self.buf.len() > self.limits.max_bulk => return Err(RedisError::ProtocolError("line too long")),
+            None => return Ok(Step::Incomplete)
+        };
+        let line = self.buf.split_to(end + 2);
+        let header = std::str::from_utf8(&line[1..end]).msg(RedisError::ProtocolError("invalid utf-8 in response"))?;
+
+        match line[0] {
+            b'+' => Ok(Step::Value(Response::Text(header.to_string()))),
+            b'-' => if self.stack.is_empty() {
+                Err(ServerError::parse(header).into())
+            } else {
+                self.error.get_or_insert_with(|| ServerError::parse(header));
+                Ok(Step::Value(Response::Nothing))
+            },
+            b':' => Ok(Step::Value(Response::Integer(header.parse().msg(RedisError::ProtocolError("parse integer response failed"))?))),
+            b'$' => match header.parse::<i64>().msg(RedisError::ProtocolError("parse bytes length failed"))? {
+                x if x < 0 => Ok(Step::Value(Response::Nothing)),
+                x if x as u64 > self.limits.max_bulk as u64 => Err(RedisError::ProtocolError("bulk string too long")),
+                x => {
+                    self.bulk = Some(x as _);
+                    self.step()
+                }
+            },
+            b'*' => match header.parse::<i64>().msg(RedisError::ProtocolError("parse array length failed"))? {
+                x if x < 0 => Ok(Step::Value(Response::Nothing)),
+                0 => Ok(Step::Value(Response::List(vec![]))),
+                x if x as u64 > self.limits.max_array as u64 => Err(RedisError::ProtocolError("array too long")),
+                x => {
+                    // the length is untrusted, so only preallocate a little
+                    self.stack.push((Vec::with_capacity((x as usize).min(1024)), x as _));
+                    Ok(Step::Nested)
+                }
+            },
+            _ => Err(RedisError::ProtocolError("unknown response type"))
+        }
+    }
+}
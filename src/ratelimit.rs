@@ -0,0 +1,121 @@
+use crate::*;
+use crate::lock::new_token;
+use std::borrow::Borrow;
+use std::time::Duration;
+
+// all scripts use the server time so clients with skewed clocks agree with each other.
+// they return {allowed, remaining, milliseconds until the quota is fully restored or until the next request is allowed}
+
+static FIXED_WINDOW: Script = Script::from_static("
+local n = redis.call('incr', KEYS[1])
+if n == 1 or redis.call('pttl', KEYS[1]) < 0 then
+    redis.call('pexpire', KEYS[1], ARGV[1])
+end
+local ttl = redis.call('pttl', KEYS[1])
+if n <= tonumber(ARGV[2]) then
+    return {1, tonumber(ARGV[2]) - n, ttl}
+else
+    return {0, 0, ttl}
+end");
+
+static SLIDING_LOG: Script = Script::from_static("
+local t = redis.call('time')
+local now = t[1] * 1000 + math.floor(t[2] / 1000)
+local window, limit = tonumber(ARGV[1]), tonumber(ARGV[2])
+redis.call('zremrangebyscore', KEYS[1], '-inf', now - window)
+local n = redis.call('zcard', KEYS[1])
+if n < limit then
+    redis.call('zadd', KEYS[1], now, ARGV[3])
+    redis.call('pexpire', KEYS[1], window)
+    return {1, limit - n - 1, window}
+end
+local oldest = redis.call('zrange', KEYS[1], 0, 0, 'withscores')
+return {0, 0, tonumber(oldest[2]) + window - now}");
+
+static TOKEN_BUCKET: Script = Script::from_static("
+local t = redis.call('time')
+local now = t[1] * 1000 + math.floor(t[2] / 1000)
+local capacity, interval = tonumber(ARGV[1]), tonumber(ARGV[2])
+local state = redis.call('hmget', KEYS[1], 'tokens', 'ts')
+local tokens = math.min(capacity, (tonumber(state[1]) or capacity) + (now - (tonumber(state[2]) or now)) / interval)
+local allowed = tokens >= 1
+if allowed then
+    tokens = tokens - 1
+end
+redis.call('hset', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('pexpire', KEYS[1], math.ceil(capacity * interval))
+if allowed then
+    return {1, math.floor(tokens), math.ceil((capacity - tokens) * interval)}
+else
+    return {0, 0, math.ceil((1 - tokens) * interval)}
+end");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitStrategy {
+    /// at most `limit` requests in each window. Cheap but allows bursts of 2x `limit` around window boundaries.
+    FixedWindow { limit: u64, window: Duration },
+    /// at most `limit` requests in any period of `window`. Memory grows with `limit`.
+    SlidingLog { limit: u64, window: Duration },
+    /// a bucket of `capacity` tokens which refills one token each `interval`. Each request takes a token.
+    TokenBucket { capacity: u64, interval: Duration }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimit {
+    /// `reset_after` is when the quota is fully restored: the end of the window for `FixedWindow`, the expiry of this
+    /// request for `SlidingLog`, and when the bucket is full again for `TokenBucket`
+    Allowed { remaining: u64, reset_after: Duration },
+    Denied { retry_after: Duration }
+}
+
+impl RateLimit {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, RateLimit::Allowed { .. })
+    }
+}
+
+/// RateLimiter keeps a separate quota for each id under the key prefix.
+pub struct RateLimiter<A, C, K>
+{
+    client: C,
+    prefix: K,
+    strategy: RateLimitStrategy,
+    phantom: std::marker::PhantomData<A>
+}
+
+impl<A, C: Deref<Target=A>, K: Borrow<[u8]>> RateLimiter<A, C, K> where for<'a> &'a A: AsRedis {
+    /// fail if the limit is 0 or the period is shorter than 1ms, which the scripts cannot handle
+    pub fn new(client: C, prefix: K, strategy: RateLimitStrategy) -> Result<Self, RedisError> {
+        let (limit, period) = match strategy {
+            RateLimitStrategy::FixedWindow { limit, window } | RateLimitStrategy::SlidingLog { limit, window } => (limit, window),
+            RateLimitStrategy::TokenBucket { capacity, interval } => (capacity, interval)
+        };
+        if limit == 0 || period < Duration::from_millis(1) {
+            return Err(RedisError::OtherError(format!("invalid rate limit strategy {:?}", strategy)))
+        }
+        Ok(Self { client, prefix, strategy, phantom: std::marker::PhantomData })
+    }
+
+    /// consume one request from the quota of `id` if allowed
+    pub fn check(&self, id: impl Borrow<[u8]>) -> Result<RateLimit, RedisError> {
+        let key = [self.prefix.borrow(), id.borrow()].concat();
+        let key: [&dyn Collection; 1] = [&RawKey(&key)];
+
+        let res: Vec<i64> = match self.strategy {
+            RateLimitStrategy::FixedWindow { limit, window } => FIXED_WINDOW.invoke(&*self.client, &key, &[&window, &limit])?,
+            RateLimitStrategy::SlidingLog { limit, window } => SLIDING_LOG.invoke(&*self.client, &key, &[&window, &limit, &new_token()])?,
+            RateLimitStrategy::TokenBucket { capacity, interval } => TOKEN_BUCKET.invoke(&*self.client, &key, &[&capacity, &interval])?
+        };
+
+        match res[..] {
+            [1, remaining, wait] => Ok(RateLimit::Allowed { remaining: remaining as _, reset_after: Duration::from_millis(wait.max(0) as _) }),
+            [0, _, wait] => Ok(RateLimit::Denied { retry_after: Duration::from_millis(wait.max(0) as _) }),
+            _ => Err(RedisError::ProtocolError("unexpected rate limit response"))
+        }
+    }
+
+    /// forget the quota of `id`
+    pub fn reset(&self, id: impl Borrow<[u8]>) -> Result<(), RedisError> {
+        self.client.arg(b"del").arg([self.prefix.borrow(), id.borrow()].concat()).fetch().map(|x| x.ignore())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::*;
+use std::borrow::{Borrow, Cow};
+use std::sync::OnceLock;
+
+/// A Lua script. It is sent by hash with `evalsha` first, and the source is only sent when Redis does not have it cached.
+#[derive(Debug, Clone)]
+pub struct Script {
+    source: Cow<'static, str>,
+    hash: OnceLock<String> // computed on first use
+}
+
+impl Script {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self { source: Cow::Owned(source.into()), hash: OnceLock::new() }
+    }
+
+    /// a script that can be stored in a `static`, so its hash is computed only once
+    pub const fn from_static(source: &'static str) -> Self {
+        Self { source: Cow::Borrowed(source), hash: OnceLock::new() }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// the lowercase hex SHA1 digest that Redis uses to identify the script
+    pub fn hash(&self) -> &str {
+        self.hash.get_or_init(|| sha1_smol::Sha1::from(&*self.source).digest().to_string())
+    }
+
+    /// run the script with `keys` and `args` (available in Lua as `KEYS` and `ARGV`) and convert the reply.
+    /// Each of `args` is encoded by `ToArg`, so numbers and durations can be passed without formatting them first.
+    pub fn invoke<R: FromResponse>(&self, client: impl AsRedis, keys: &[&dyn Collection], args: &[&dyn ToArg]) -> Result<R, RedisError> {
+        let mut sess = Session::new(client.as_redis());
+        match self.run(&mut sess, b"evalsha", self.hash().as_bytes(), keys, args) {
+            Err(e) if e.kind() == Some(&ServerErrorKind::NoScript) => self.run(&mut sess, b"eval", self.source.as_bytes(), keys, args),
+            res => res
+        }.and_then(R::from_response)
+    }
+
+    /// like `invoke`, but `args` are encoded with `serializer`, e.g. the value serializer of a collection in `keys`.
+    pub fn invoke_with<V, R: FromResponse>(&self, client: impl AsRedis, keys: &[&dyn Collection], serializer: fn(x: &V) -> Box<[u8]>, args: &[impl Borrow<V>]) -> Result<R, RedisError> {
+        let args: Vec<_> = args.iter().map(|x| serializer(x.borrow())).collect();
+        let mut sess = Session::new(client.as_redis());
+        match self.run(&mut sess, b"evalsha", self.hash().as_bytes(), keys, &args) {
+            Err(e) if e.kind() == Some(&ServerErrorKind::NoScript) => self.run(&mut sess, b"eval", self.source.as_bytes(), keys, &args),
+            res => res
+        }.and_then(R::from_response)
+    }
+
+    fn run<T: Read + Write, P: DerefMut<Target=T>>(&self, sess: &mut Session<P>, cmd: &[u8], script: &[u8], keys: &[&dyn Collection], args: &[impl ToArg]) -> Result<Response, RedisError> {
+        sess.arg(cmd).arg(script).arg(keys.len());
+        for key in keys {
+            sess.arg(key.key());
+        }
+        sess.arg(args).fetch()
+    }
+
+    /// load the script into the script cache without running it
+    pub fn load(&self, client: impl AsRedis) -> Result<(), RedisError> {
+        client.arg(b"script").arg(b"load").arg(self.source.as_bytes()).fetch().map(|x| x.ignore())
+    }
+
+    /// check whether the script is in the script cache
+    pub fn exists(&self, client: impl AsRedis) -> Result<bool, RedisError> {
+        client.arg(b"script").arg(b"exists").arg(self.hash().as_bytes()).fetch().map(|x| x.list()[0].as_integer() == 1)
+    }
+
+    /// remove all scripts from the script cache
+    pub fn flush(client: impl AsRedis) -> Result<(), RedisError> {
+        client.arg(b"script").arg(b"flush").fetch().map(|x| x.ignore())
+    }
+}
@@ -0,0 +1,118 @@
+//! Building blocks for Redis-compatible servers: a request decoder, a reply encoder and a threaded listener that
+//! dispatches commands to a `Handler`.
+
+use crate::*;
+use std::net::TcpListener;
+use std::os::unix::net::UnixListener;
+
+/// Serves commands. Errors are sent to the client as error replies, e.g. `Err("ERR unknown command".into())`.
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, args: &[Bytes]) -> Result<Response, String>;
+}
+
+impl<F: Fn(&[Bytes]) -> Result<Response, String> + Send + Sync + 'static> Handler for F {
+    fn handle(&self, args: &[Bytes]) -> Result<Response, String> {
+        self(args)
+    }
+}
+
+/// read a command, either a RESP array of bulk strings or an inline command. Return None at the end of stream.
+/// The parser keeps the bytes after the command, so it must be reused for the following commands of the stream.
+pub fn read_command(parser: &mut RespParser, r: &mut impl Read) -> Result<Option<Vec<Bytes>>, RedisError> {
+    if parser.buffered() == 0 && parser.read_from(r)? == 0 {
+        return Ok(None)
+    }
+    match parser.peek() {
+        Some(b'*') => match parser.read_reply(r)? {
+            Response::List(x) => x.into_iter().map(|x| match x {
+                Response::Bytes(x) => Ok(x),
+                _ => Err(RedisError::ProtocolError("command arguments must be bulk strings"))
+            }).collect::<Result<_, _>>().map(Some),
+            _ => Err(RedisError::ProtocolError("invalid command"))
+        },
+        _ => {
+            let line = parser.read_line(r)?;
+            Ok(Some(line.split(|x| x.is_ascii_whitespace()).filter(|x| !x.is_empty()).map(|x| line.slice_ref(x)).collect()))
+        }
+    }
+}
+
+/// write a reply in RESP2
+pub fn write_reply(w: &mut impl Write, x: &Result<Response, String>) -> std::io::Result<()> {
+    match x {
+        Ok(x) => x.write_resp(w),
+        Err(e) => write!(w, "-{}\r\n", e.replace(['\r', '\n'], " "))
+    }
+}
+
+/// A threaded server. Each connection is served by its own thread.
+pub struct Server<H> {
+    handler: Arc<H>,
+    limits: RespLimits
+}
+
+impl<H> Clone for Server<H> {
+    fn clone(&self) -> Self {
+        Self { handler: self.handler.clone(), limits: self.limits }
+    }
+}
+
+impl<H: Handler> Server<H> {
+    pub fn new(handler: H) -> Self {
+        Self { handler: Arc::new(handler), limits: RespLimits::default() }
+    }
+
+    /// limit the size of commands. Clients sending larger ones get an error reply and are disconnected.
+    pub fn limits(mut self, limits: RespLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// accept connections forever
+    pub fn serve_tcp(&self, listener: TcpListener) -> std::io::Result<()> {
+        for stream in listener.incoming() {
+            self.spawn(stream?)
+        }
+        Ok(())
+    }
+
+    /// accept connections forever
+    pub fn serve_unix(&self, listener: UnixListener) -> std::io::Result<()> {
+        for stream in listener.incoming() {
+            self.spawn(stream?)
+        }
+        Ok(())
+    }
+
+    fn spawn(&self, stream: impl Read + Write + Send + 'static) {
+        let server = self.clone();
+        std::thread::spawn(move || server.serve(stream));
+    }
+
+    /// serve a single connection until it is closed. Replies to pipelined commands are sent together.
+    pub fn serve(&self, mut stream: impl Read + Write) -> Result<(), RedisError> {
+        let mut parser = RespParser::new(self.limits);
+        let mut out = vec![];
+        loop {
+            let args = match read_command(&mut parser, &mut stream) {
+                Ok(Some(x)) => x,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    if let RedisError::ProtocolError(msg) = e {
+                        write!(out, "-ERR Protocol error: {}\r\n", msg)?;
+                        stream.write_all(&out)?
+                    }
+                    return Err(e)
+                }
+            };
+            if args.is_empty() {
+                continue
+            }
+            write_reply(&mut out, &self.handler.handle(&args))?;
+            if parser.buffered() == 0 {
+                stream.write_all(&out)?;
+                out.clear()
+            }
+        }
+    }
+}
@@ -0,0 +1,1070 @@
+//! An in-process fake Redis for hermetic tests. It speaks RESP over in-memory connections and implements the string,
+//! bitmap, list and hash commands used by the collections, including expiration of keys and hash fields, blocking pops
+//! and transactions.
+//! For asserting the exact commands sent, `MockConnection` replies from a script instead.
+
+use crate::*;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// A fake Redis server. Clones share the same data.
+#[derive(Clone, Default)]
+pub struct FakeRedis {
+    db: Arc<(Mutex<Db>, Condvar)>
+}
+
+impl FakeRedis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// open a new connection to the server
+    pub fn connect(&self) -> FakeConnection {
+        FakeConnection { db: self.db.clone(), input: vec![], output: vec![], pos: 0, watched: vec![], queued: None }
+    }
+}
+
+/// Like `TcpClient`, each session opens a new connection.
+impl AsRedis for &FakeRedis {
+    type T = FakeConnection;
+    type P = Box<FakeConnection>;
+    fn as_redis(self) -> Self::P {
+        Box::new(self.connect())
+    }
+}
+
+/// An in-memory duplex stream. Commands are executed as soon as they are completely written,
+/// so writing a blocking command blocks until it is served.
+pub struct FakeConnection {
+    db: Arc<(Mutex<Db>, Condvar)>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    pos: usize,
+    watched: Vec<(Vec<u8>, u64)>,
+    queued: Option<Vec<Vec<Vec<u8>>>> // commands in MULTI
+}
+
+impl Write for FakeConnection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.input.extend_from_slice(buf);
+        while let Some((len, args)) = parse_command(&self.input) {
+            let args: Vec<Vec<u8>> = args.into_iter().map(|(start, end)| self.input[start..end].to_vec()).collect();
+            self.input.drain(..len);
+            let reply = self.execute(args);
+            reply.write(&mut self.output)
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for FakeConnection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.output.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::WouldBlock, "read without pending reply"))
+        }
+
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        if self.pos == self.output.len() {
+            self.output.clear();
+            self.pos = 0
+        }
+        Ok(n)
+    }
+}
+
+impl FakeConnection {
+    fn execute(&mut self, args: Vec<Vec<u8>>) -> Reply {
+        let name = match args.first() {
+            Some(x) => x.to_ascii_lowercase(),
+            None => return Reply::Error("ERR empty command".into())
+        };
+        let (lock, cond) = &*self.db;
+        let mut db = lock.lock().unwrap();
+
+        if let Some(queued) = &mut self.queued {
+            return match &name[..] {
+                b"exec" => {
+                    let queued = self.queued.take().unwrap();
+                    let watched = std::mem::take(&mut self.watched);
+                    if watched.iter().any(|(key, version)| db.version(key) != *version) {
+                        return Reply::Array(None)
+                    }
+                    let replies = queued.iter().map(|x| db.execute(x)).collect();
+                    cond.notify_all();
+                    Reply::Array(Some(replies))
+                },
+                b"discard" => {
+                    self.queued = None;
+                    self.watched.clear();
+                    Reply::ok()
+                },
+                b"multi" => Reply::Error("ERR MULTI calls can not be nested".into()),
+                b"watch" => Reply::Error("ERR WATCH inside MULTI is not allowed".into()),
+                _ => {
+                    queued.push(args);
+                    Reply::Status("QUEUED".into())
+                }
+            }
+        }
+
+        match &name[..] {
+            b"multi" => {
+                self.queued = Some(vec![]);
+                Reply::ok()
+            },
+            b"watch" => {
+                for key in &args[1..] {
+                    let version = db.version(key);
+                    self.watched.push((key.clone(), version))
+                }
+                Reply::ok()
+            },
+            b"unwatch" => {
+                self.watched.clear();
+                Reply::ok()
+            },
+            b"exec" => Reply::Error("ERR EXEC without MULTI".into()),
+            b"discard" => Reply::Error("ERR DISCARD without MULTI".into()),
+            b"blpop" if args.len() >= 3 => {
+                let timeout = match parse_number::<f64>(&args[args.len() - 1]) {
+                    Ok(x) if x >= 0.0 => x,
+                    _ => return Reply::Error("ERR timeout is not a float or out of range".into())
+                };
+                let deadline = Instant::now() + Duration::from_secs_f64(timeout);
+                loop {
+                    for key in &args[1..args.len() - 1] {
+                        match db.list_mut(key, false) {
+                            Ok(Some(list)) => if let Some(x) = list.pop_front() {
+                                db.cleanup(key);
+                                db.touch(key);
+                                return Reply::Array(Some(vec![Reply::Bulk(Some(key.clone())), Reply::Bulk(Some(x))]))
+                            },
+                            Ok(None) => {},
+                            Err(e) => return e
+                        }
+                    }
+
+                    let now = Instant::now();
+                    if timeout == 0.0 {
+                        db = cond.wait(db).unwrap()
+                    } else if now < deadline {
+                        db = cond.wait_timeout(db, deadline - now).unwrap().0
+                    } else {
+                        return Reply::Array(None)
+                    }
+                }
+            },
+            _ => {
+                let reply = db.execute(&args);
+                cond.notify_all();
+                reply
+            }
+        }
+    }
+}
+
+/// A scripted connection that checks every command against a queue of expectations and replies with the scripted
+/// response. Writing an unexpected command panics with both commands in the message.
+#[derive(Default)]
+pub struct MockConnection {
+    input: Vec<u8>,
+    output: Vec<u8>,
+    pos: usize,
+    expected: VecDeque<(Vec<Vec<u8>>, Reply)>,
+    commands: Vec<Vec<Vec<u8>>>
+}
+
+/// A pending expectation, finished by giving its reply.
+pub struct Expectation<'m> {
+    mock: &'m mut MockConnection,
+    args: Vec<Vec<u8>>
+}
+
+impl<'m> Expectation<'m> {
+    pub fn reply(self, x: Response) -> &'m mut MockConnection {
+        self.respond(Reply::from(x))
+    }
+
+    /// reply with an error, e.g. `error("ERR wrong number of arguments")`
+    pub fn error(self, msg: &str) -> &'m mut MockConnection {
+        self.respond(Reply::Error(msg.into()))
+    }
+
+    fn respond(self, reply: Reply) -> &'m mut MockConnection {
+        self.mock.expected.push_back((self.args, reply));
+        self.mock
+    }
+}
+
+impl MockConnection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// expect the next command to be exactly `args`. Command names are compared case-insensitively.
+    pub fn expect(&mut self, args: &[&[u8]]) -> Expectation<'_> {
+        Expectation { mock: self, args: args.iter().map(|x| x.to_vec()).collect() }
+    }
+
+    /// all commands received so far
+    pub fn commands(&self) -> &[Vec<Vec<u8>>] {
+        &self.commands
+    }
+
+    /// panic if some expected commands were never sent
+    pub fn verify(&self) {
+        if !self.expected.is_empty() {
+            let rest: Vec<_> = self.expected.iter().map(|(x, _)| format_command(x)).collect();
+            panic!("{} expected command(s) not sent: {}", rest.len(), rest.join(", "))
+        }
+    }
+}
+
+impl Write for MockConnection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.input.extend_from_slice(buf);
+        while let Some((len, args)) = parse_command(&self.input) {
+            let args: Vec<Vec<u8>> = args.into_iter().map(|(start, end)| self.input[start..end].to_vec()).collect();
+            self.input.drain(..len);
+            let (expected, reply) = match self.expected.pop_front() {
+                Some(x) => x,
+                None => panic!("unexpected command {}: no more commands expected", format_command(&args))
+            };
+            let matches = expected.len() == args.len() && expected.iter().zip(&args).enumerate().all(|(i, (x, y))| {
+                if i == 0 { x.eq_ignore_ascii_case(y) } else { x == y }
+            });
+            if !matches {
+                panic!("unexpected command {}: expected {}", format_command(&args), format_command(&expected))
+            }
+            reply.write(&mut self.output);
+            self.commands.push(args)
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for MockConnection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.output.len() {
+            return Err(std::io::Error::new(std::io::ErrorKind::WouldBlock, "read without pending reply"))
+        }
+
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        if self.pos == self.output.len() {
+            self.output.clear();
+            self.pos = 0
+        }
+        Ok(n)
+    }
+}
+
+// redis-cli style quoting, e.g. `"rpush" "k" "1"`
+fn format_command(args: &[Vec<u8>]) -> String {
+    args.iter().map(|x| format!("{:?}", String::from_utf8_lossy(x))).collect::<Vec<_>>().join(" ")
+}
+
+enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Reply>>)
+}
+
+impl Reply {
+    fn ok() -> Self {
+        Reply::Status("OK".into())
+    }
+
+    fn wrong_type() -> Self {
+        Reply::Error("WRONGTYPE Operation against a key holding the wrong kind of value".into())
+    }
+
+    fn syntax() -> Self {
+        Reply::Error("ERR syntax error".into())
+    }
+
+    fn bit_offset() -> Self {
+        Reply::Error("ERR bit offset is not an integer or out of range".into())
+    }
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        match self {
+            Reply::Status(x) => write!(buf, "+{}\r\n", x),
+            Reply::Error(x) => write!(buf, "-{}\r\n", x),
+            Reply::Integer(x) => write!(buf, ":{}\r\n", x),
+            Reply::Bulk(None) => write!(buf, "$-1\r\n"),
+            Reply::Bulk(Some(x)) => {
+                write!(buf, "${}\r\n", x.len()).expect("bug");
+                buf.extend_from_slice(x);
+                write!(buf, "\r\n")
+            },
+            Reply::Array(None) => write!(buf, "*-1\r\n"),
+            Reply::Array(Some(x)) => {
+                write!(buf, "*{}\r\n", x.len()).expect("bug");
+                for reply in x {
+                    reply.write(buf)
+                }
+                Ok(())
+            }
+        }.expect("bug")
+    }
+}
+
+impl From<Response> for Reply {
+    fn from(x: Response) -> Self {
+        match x {
+            Response::Integer(x) => Reply::Integer(x),
+            Response::Text(x) => Reply::Status(x),
+            Response::Bytes(x) => Reply::Bulk(Some(x.to_vec())),
+            Response::List(x) => Reply::Array(Some(x.into_iter().map(Reply::from).collect())),
+            Response::Nothing => Reply::Bulk(None)
+        }
+    }
+}
+
+fn parse_number<N: std::str::FromStr>(x: &[u8]) -> Result<N, Reply> {
+    std::str::from_utf8(x).ok().and_then(|x| x.parse().ok()).ok_or_else(|| Reply::Error("ERR value is not an integer or out of range".into()))
+}
+
+// the arguments after `FIELDS numfields` starting at `at`, which are `numfields` groups of `size` arguments
+fn hash_fields(args: &[Vec<u8>], at: usize, size: usize) -> Result<&[Vec<u8>], Reply> {
+    match args.get(at..at + 2) {
+        Some([name, n]) if name.eq_ignore_ascii_case(b"fields") => {
+            let n: usize = parse_number(n)?;
+            if n == 0 || args.len() - at - 2 != n * size {
+                return Err(Reply::Error("ERR numfields should be greater than 0 and match the provided number of fields".into()))
+            }
+            Ok(&args[at + 2..])
+        },
+        _ => Err(Reply::syntax())
+    }
+}
+
+// strings are limited to 512MB, so bit offsets must be below 2^32
+const MAX_BITS: usize = 1 << 32;
+
+fn parse_offset(x: &[u8]) -> Result<usize, Reply> {
+    parse_number::<usize>(x).ok().filter(|&x| x < MAX_BITS).ok_or_else(Reply::bit_offset)
+}
+
+// the instant after `x` times `unit` milliseconds. Times that overflow are rejected like Redis does.
+fn deadline(x: &[u8], unit: u64, cmd: &str) -> Result<Instant, Reply> {
+    parse_number::<u64>(x)?.checked_mul(unit)
+        .filter(|&x| x <= i64::MAX as u64)
+        .and_then(|x| Instant::now().checked_add(Duration::from_millis(x)))
+        .ok_or_else(|| Reply::Error(format!("ERR invalid expire time in '{}' command", cmd)))
+}
+
+fn format_float(x: f64) -> Vec<u8> {
+    let mut s = format!("{:.17}", x);
+    while s.contains('.') && (s.ends_with('0') || s.ends_with('.')) {
+        s.pop();
+    }
+    s.into_bytes()
+}
+
+// Redis style index normalization: negative indexes count from the end. Return None if the range is empty.
+fn normalize_range(start: i64, end: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { (start + len).max(0) } else { start };
+    let end = if end < 0 { end + len } else { end.min(len - 1) };
+    if start > end || start >= len {
+        None
+    } else {
+        Some((start as usize, end as usize))
+    }
+}
+
+enum Value {
+    String(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+    Hash(HashMap<Vec<u8>, Vec<u8>>)
+}
+
+#[derive(Default)]
+struct Db {
+    entries: HashMap<Vec<u8>, (Value, Option<Instant>)>,
+    field_expires: HashMap<Vec<u8>, HashMap<Vec<u8>, Instant>>, // hash fields with a ttl
+    versions: HashMap<Vec<u8>, u64>, // for WATCH
+    clock: u64
+}
+
+macro_rules! getter {
+    ($name: ident, $variant: ident, $ty: ty) => {
+        /// get the value of `key` if it has the right type, optionally creating an empty one if it does not exist
+        fn $name(&mut self, key: &[u8], create: bool) -> Result<Option<&mut $ty>, Reply> {
+            self.expire(key);
+            if create && !self.entries.contains_key(key) {
+                self.entries.insert(key.to_vec(), (Value::$variant(Default::default()), None));
+            }
+            match self.entries.get_mut(key) {
+                Some((Value::$variant(x), _)) => Ok(Some(x)),
+                Some(_) => Err(Reply::wrong_type()),
+                None => Ok(None)
+            }
+        }
+    }
+}
+
+impl Db {
+    getter!(string_mut, String, Vec<u8>);
+    getter!(list_mut, List, VecDeque<Vec<u8>>);
+    getter!(hash_mut, Hash, HashMap<Vec<u8>, Vec<u8>>);
+
+    fn expire(&mut self, key: &[u8]) {
+        if let Some((_, Some(at))) = self.entries.get(key) {
+            if *at <= Instant::now() {
+                self.entries.remove(key);
+                self.touch(key)
+            }
+        }
+        self.expire_fields(key)
+    }
+
+    // remove the expired fields of a hash, and forget the field ttls of a hash that was removed or replaced
+    fn expire_fields(&mut self, key: &[u8]) {
+        let expires = match self.field_expires.get_mut(key) {
+            Some(x) => x,
+            None => return
+        };
+        let hash = match self.entries.get_mut(key) {
+            Some((Value::Hash(x), _)) => x,
+            _ => {
+                self.field_expires.remove(key);
+                return
+            }
+        };
+
+        let now = Instant::now();
+        let len = hash.len();
+        expires.retain(|field, at| if *at <= now {
+            hash.remove(field);
+            false
+        } else {
+            true
+        });
+        if expires.is_empty() {
+            self.field_expires.remove(key);
+        }
+        if hash.len() < len {
+            self.cleanup(key);
+            self.touch(key)
+        }
+    }
+
+    // remove the ttl of a hash field. Return whether it had one.
+    fn persist_field(&mut self, key: &[u8], field: &[u8]) -> bool {
+        let removed = self.field_expires.get_mut(key).is_some_and(|x| x.remove(field).is_some());
+        if self.field_expires.get(key).is_some_and(|x| x.is_empty()) {
+            self.field_expires.remove(key);
+        }
+        removed
+    }
+
+    fn touch(&mut self, key: &[u8]) {
+        self.clock += 1;
+        self.versions.insert(key.to_vec(), self.clock);
+    }
+
+    fn version(&mut self, key: &[u8]) -> u64 {
+        self.expire(key);
+        self.versions.get(key).cloned().unwrap_or(0)
+    }
+
+    // Redis removes empty lists and hashes
+    fn cleanup(&mut self, key: &[u8]) {
+        let empty = match self.entries.get(key) {
+            Some((Value::List(x), _)) => x.is_empty(),
+            Some((Value::Hash(x), _)) => x.is_empty(),
+            _ => false
+        };
+        if empty {
+            self.entries.remove(key);
+        }
+    }
+
+    fn execute(&mut self, args: &[Vec<u8>]) -> Reply {
+        self.dispatch(args).unwrap_or_else(|e| e)
+    }
+
+    fn dispatch(&mut self, args: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let name = args[0].to_ascii_lowercase();
+        match (&name[..], &args[1..]) {
+            (b"ping", []) => Ok(Reply::Status("PONG".into())),
+            (b"flushdb", []) | (b"flushall", []) => {
+                let keys: Vec<_> = self.entries.keys().cloned().collect();
+                for key in keys {
+                    self.touch(&key)
+                }
+                self.entries.clear();
+                Ok(Reply::ok())
+            },
+            (b"del", keys) if !keys.is_empty() => Ok(Reply::Integer(keys.iter().filter(|key| {
+                self.expire(key);
+                let removed = self.entries.remove(&key[..]).is_some();
+                if removed {
+                    self.touch(key)
+                }
+                removed
+            }).count() as _)),
+            (b"exists", keys) if !keys.is_empty() => Ok(Reply::Integer(keys.iter().filter(|key| {
+                self.expire(key);
+                self.entries.contains_key(&key[..])
+            }).count() as _)),
+            (b"expire", [key, ttl]) => self.set_expire(key, deadline(ttl, 1000, "expire")?),
+            (b"pexpire", [key, ttl]) => self.set_expire(key, deadline(ttl, 1, "pexpire")?),
+            (b"ttl", [key]) => self.ttl(key).map(|x| if x > 0 { (x + 500) / 1000 } else { x }).map(Reply::Integer),
+            (b"pttl", [key]) => self.ttl(key).map(Reply::Integer),
+            (b"get", [key]) => self.string_mut(key, false).map(|x| Reply::Bulk(x.cloned())),
+            (b"set", [key, value, options @ ..]) => self.set(key, value, options),
+            (b"getbit", [key, offset]) => {
+                let offset = parse_offset(offset)?;
+                Ok(Reply::Integer(self.string_mut(key, false)?.map(|x| get_bit(x, offset) as i64).unwrap_or(0)))
+            },
+            (b"setbit", [key, offset, value]) => {
+                let offset = parse_offset(offset)?;
+                let value = match &value[..] {
+                    b"0" => false,
+                    b"1" => true,
+                    _ => return Err(Reply::Error("ERR bit is not an integer or out of range".into()))
+                };
+                let x = self.string_mut(key, true)?.unwrap();
+                let old = get_bit(x, offset);
+                set_bit(x, offset, value);
+                self.touch(key);
+                Ok(Reply::Integer(old as _))
+            },
+            (b"bitcount", [key, range @ ..]) => self.bitcount(key, range),
+            (b"bitpos", [key, bit, range @ ..]) => self.bitpos(key, bit, range),
+            (b"bitop", [op, dest, keys @ ..]) if !keys.is_empty() => self.bitop(op, dest, keys),
+            (b"bitfield", [key, ops @ ..]) => self.bitfield(key, ops),
+            (b"rpush", [key, values @ ..]) | (b"lpush", [key, values @ ..]) if !values.is_empty() => {
+                let list = self.list_mut(key, true)?.unwrap();
+                for value in values {
+                    if name == b"rpush" {
+                        list.push_back(value.clone())
+                    } else {
+                        list.push_front(value.clone())
+                    }
+                }
+                let len = list.len();
+                self.touch(key);
+                Ok(Reply::Integer(len as _))
+            },
+            (b"rpop", [key]) | (b"lpop", [key]) => {
+                let value = match self.list_mut(key, false)? {
+                    Some(list) if name == b"rpop" => list.pop_back(),
+                    Some(list) => list.pop_front(),
+                    None => None
+                };
+                if value.is_some() {
+                    self.cleanup(key);
+                    self.touch(key)
+                }
+                Ok(Reply::Bulk(value))
+            },
+            (b"llen", [key]) => Ok(Reply::Integer(self.list_mut(key, false)?.map(|x| x.len()).unwrap_or(0) as _)),
+            (b"lindex", [key, index]) => {
+                let index: i64 = parse_number(index)?;
+                Ok(Reply::Bulk(self.list_mut(key, false)?.and_then(|list| {
+                    let index = if index < 0 { index + list.len() as i64 } else { index };
+                    list.get(usize::try_from(index).ok()?).cloned()
+                })))
+            },
+            (b"lset", [key, index, value]) => {
+                let index: i64 = parse_number(index)?;
+                let list = self.list_mut(key, false)?.ok_or_else(|| Reply::Error("ERR no such key".into()))?;
+                let index = if index < 0 { index + list.len() as i64 } else { index };
+                let slot = usize::try_from(index).ok().and_then(|i| list.get_mut(i)).ok_or_else(|| Reply::Error("ERR index out of range".into()))?;
+                *slot = value.clone();
+                self.touch(key);
+                Ok(Reply::ok())
+            },
+            (b"lrange", [key, start, end]) => {
+                let (start, end) = (parse_number(start)?, parse_number(end)?);
+                Ok(Reply::Array(Some(match self.list_mut(key, false)? {
+                    Some(list) => match normalize_range(start, end, list.len()) {
+                        Some((start, end)) => list.range(start..=end).map(|x| Reply::Bulk(Some(x.clone()))).collect(),
+                        None => vec![]
+                    },
+                    None => vec![]
+                })))
+            },
+            (b"lrem", [key, count, value]) => {
+                let count: i64 = parse_number(count)?;
+                let list = match self.list_mut(key, false)? {
+                    Some(list) => list,
+                    None => return Ok(Reply::Integer(0))
+                };
+                let limit = if count == 0 { usize::MAX } else { count.unsigned_abs() as usize };
+                let mut positions: Vec<_> = (0..list.len()).filter(|&i| list[i] == *value).collect();
+                if count < 0 {
+                    positions.reverse()
+                }
+                positions.truncate(limit);
+                positions.sort_unstable();
+                for i in positions.iter().rev() {
+                    list.remove(*i);
+                }
+                self.cleanup(key);
+                self.touch(key);
+                Ok(Reply::Integer(positions.len() as _))
+            },
+            (b"hset", [key, pairs @ ..]) if !pairs.is_empty() && pairs.len() % 2 == 0 => {
+                let hash = self.hash_mut(key, true)?.unwrap();
+                let added = pairs.chunks(2).filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none()).count();
+                for pair in pairs.chunks(2) {
+                    self.persist_field(key, &pair[0]);
+                }
+                self.touch(key);
+                Ok(Reply::Integer(added as _))
+            },
+            (b"hsetnx", [key, field, value]) => {
+                let hash = self.hash_mut(key, true)?.unwrap();
+                let added = !hash.contains_key(field);
+                if added {
+                    hash.insert(field.clone(), value.clone());
+                    self.touch(key)
+                }
+                Ok(Reply::Integer(added as _))
+            },
+            (b"hget", [key, field]) => Ok(Reply::Bulk(self.hash_mut(key, false)?.and_then(|x| x.get(field).cloned()))),
+            (b"hmget", [key, fields @ ..]) if !fields.is_empty() => {
+                let hash = self.hash_mut(key, false)?;
+                Ok(Reply::Array(Some(fields.iter().map(|field| Reply::Bulk(hash.as_ref().and_then(|x| x.get(field).cloned()))).collect())))
+            },
+            (b"hdel", [key, fields @ ..]) if !fields.is_empty() => {
+                let removed = match self.hash_mut(key, false)? {
+                    Some(hash) => fields.iter().filter(|field| hash.remove(&field[..]).is_some()).count(),
+                    None => 0
+                };
+                for field in fields {
+                    self.persist_field(key, field);
+                }
+                if removed > 0 {
+                    self.cleanup(key);
+                    self.touch(key)
+                }
+                Ok(Reply::Integer(removed as _))
+            },
+            (b"hexists", [key, field]) => Ok(Reply::Integer(self.hash_mut(key, false)?.map(|x| x.contains_key(field)).unwrap_or(false) as _)),
+            (b"hlen", [key]) => Ok(Reply::Integer(self.hash_mut(key, false)?.map(|x| x.len()).unwrap_or(0) as _)),
+            (b"hgetall", [key]) | (b"hkeys", [key]) | (b"hvals", [key]) => {
+                let hash = self.hash_mut(key, false)?;
+                Ok(Reply::Array(Some(hash.into_iter().flatten().flat_map(|(field, value)| match &name[..] {
+                    b"hgetall" => vec![field.clone(), value.clone()],
+                    b"hkeys" => vec![field.clone()],
+                    _ => vec![value.clone()]
+                }).map(|x| Reply::Bulk(Some(x))).collect())))
+            },
+            // returns everything in one batch
+            (b"hscan", [key, _cursor, ..]) => {
+                let hash = self.hash_mut(key, false)?;
+                let pairs = hash.into_iter().flatten().flat_map(|(field, value)| vec![field.clone(), value.clone()]).map(|x| Reply::Bulk(Some(x))).collect();
+                Ok(Reply::Array(Some(vec![Reply::Bulk(Some(b"0".to_vec())), Reply::Array(Some(pairs))])))
+            },
+            (b"hincrby", [key, field, delta]) => {
+                let delta: i64 = parse_number(delta)?;
+                let hash = self.hash_mut(key, true)?.unwrap();
+                let value = match hash.get(field) {
+                    Some(x) => parse_number::<i64>(x).map_err(|_| Reply::Error("ERR hash value is not an integer".into()))?,
+                    None => 0
+                };
+                let value = value.checked_add(delta).ok_or_else(|| Reply::Error("ERR increment or decrement would overflow".into()))?;
+                hash.insert(field.clone(), value.to_string().into_bytes());
+                self.touch(key);
+                Ok(Reply::Integer(value))
+            },
+            (b"hincrbyfloat", [key, field, delta]) => {
+                let delta: f64 = parse_number(delta).map_err(|_| Reply::Error("ERR value is not a valid float".into()))?;
+                let hash = self.hash_mut(key, true)?.unwrap();
+                let value = match hash.get(field) {
+                    Some(x) => parse_number::<f64>(x).map_err(|_| Reply::Error("ERR hash value is not a float".into()))?,
+                    None => 0.0
+                } + delta;
+                hash.insert(field.clone(), format_float(value));
+                self.touch(key);
+                Ok(Reply::Bulk(Some(format_float(value))))
+            },
+            (b"hpexpire", [key, args @ ..]) => self.hpexpire(key, args),
+            (b"hpttl", [key, args @ ..]) => {
+                let fields = hash_fields(args, 0, 1)?;
+                let hash = self.hash_mut(key, false)?.map(|x| fields.iter().map(|field| x.contains_key(field)).collect());
+                let now = Instant::now();
+                Ok(Reply::Array(Some(hash.unwrap_or_else(|| vec![false; fields.len()]).into_iter().zip(fields).map(|(exists, field)| {
+                    match self.field_expires.get(key).and_then(|x| x.get(field)) {
+                        _ if !exists => Reply::Integer(-2),
+                        Some(at) => Reply::Integer(at.saturating_duration_since(now).as_millis() as _),
+                        None => Reply::Integer(-1)
+                    }
+                }).collect())))
+            },
+            (b"hpersist", [key, args @ ..]) => {
+                let fields = hash_fields(args, 0, 1)?;
+                let hash = self.hash_mut(key, false)?.map(|x| fields.iter().map(|field| x.contains_key(field)).collect());
+                let replies: Vec<_> = hash.unwrap_or_else(|| vec![false; fields.len()]).into_iter().zip(fields).map(|(exists, field)| match exists {
+                    false => -2,
+                    true if self.persist_field(key, field) => 1,
+                    true => -1
+                }).collect();
+                if replies.contains(&1) {
+                    self.touch(key)
+                }
+                Ok(Reply::Array(Some(replies.into_iter().map(Reply::Integer).collect())))
+            },
+            (b"hsetex", [key, args @ ..]) => self.hsetex(key, args),
+            (b"hgetex", [key, args @ ..]) => self.hgetex(key, args),
+            _ => Err(Reply::Error(format!("ERR unknown command or wrong number of arguments for '{}'", String::from_utf8_lossy(&name))))
+        }
+    }
+
+    fn set_expire(&mut self, key: &[u8], at: Instant) -> Result<Reply, Reply> {
+        self.expire(key);
+        match self.entries.get_mut(key) {
+            Some((_, expire)) => {
+                *expire = Some(at);
+                self.touch(key);
+                Ok(Reply::Integer(1))
+            },
+            None => Ok(Reply::Integer(0))
+        }
+    }
+
+    fn hpexpire(&mut self, key: &[u8], args: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let options = args.iter().position(|x| x.eq_ignore_ascii_case(b"fields")).unwrap_or(args.len());
+        let fields = hash_fields(args, options, 1)?;
+        let at = deadline(args.first().ok_or_else(Reply::syntax)?, 1, "hpexpire")?;
+        let condition = match &args[1..options] {
+            [] => None,
+            [x] if [&b"nx"[..], b"xx", b"gt", b"lt"].contains(&&x.to_ascii_lowercase()[..]) => Some(x.to_ascii_lowercase()),
+            _ => return Err(Reply::syntax())
+        };
+
+        let mut replies = vec![];
+        for field in fields {
+            if !self.hash_mut(key, false)?.is_some_and(|x| x.contains_key(field)) {
+                replies.push(Reply::Integer(-2));
+                continue
+            }
+            let current = self.field_expires.get(key).and_then(|x| x.get(field)).cloned();
+            let allowed = match condition.as_deref() {
+                Some(b"nx") => current.is_none(),
+                Some(b"xx") => current.is_some(),
+                Some(b"gt") => current.is_some_and(|x| at > x),
+                Some(b"lt") => current.is_none_or(|x| at < x),
+                _ => true
+            };
+            replies.push(Reply::Integer(if !allowed {
+                0
+            } else if at <= Instant::now() {
+                self.hash_mut(key, false)?.unwrap().remove(field);
+                self.persist_field(key, field);
+                2
+            } else {
+                self.field_expires.entry(key.to_vec()).or_default().insert(field.clone(), at);
+                1
+            }));
+        }
+        if replies.iter().any(|x| matches!(x, Reply::Integer(1) | Reply::Integer(2))) {
+            self.cleanup(key);
+            self.touch(key)
+        }
+        Ok(Reply::Array(Some(replies)))
+    }
+
+    fn hsetex(&mut self, key: &[u8], args: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let options = args.iter().position(|x| x.eq_ignore_ascii_case(b"fields")).unwrap_or(args.len());
+        let pairs = hash_fields(args, options, 2)?;
+        let (mut fnx, mut fxx, mut keep, mut expire) = (false, false, false, None);
+        let mut options = args[..options].iter();
+        while let Some(option) = options.next() {
+            match &option.to_ascii_lowercase()[..] {
+                b"fnx" => fnx = true,
+                b"fxx" => fxx = true,
+                b"keepttl" => keep = true,
+                b"ex" => expire = Some(deadline(options.next().ok_or_else(Reply::syntax)?, 1000, "hsetex")?),
+                b"px" => expire = Some(deadline(options.next().ok_or_else(Reply::syntax)?, 1, "hsetex")?),
+                _ => return Err(Reply::syntax())
+            }
+        }
+
+        let existing = match self.hash_mut(key, false)? {
+            Some(hash) => pairs.chunks(2).filter(|pair| hash.contains_key(&pair[0])).count(),
+            None => 0
+        };
+        if (fnx && existing > 0) || (fxx && existing < pairs.len() / 2) {
+            return Ok(Reply::Integer(0))
+        }
+        let hash = self.hash_mut(key, true)?.unwrap();
+        for pair in pairs.chunks(2) {
+            hash.insert(pair[0].clone(), pair[1].clone());
+        }
+        for pair in pairs.chunks(2) {
+            match expire {
+                Some(at) => self.field_expires.entry(key.to_vec()).or_default().insert(pair[0].clone(), at).ignore(),
+                None if keep => {},
+                None => self.persist_field(key, &pair[0]).ignore()
+            }
+        }
+        self.touch(key);
+        Ok(Reply::Integer(1))
+    }
+
+    fn hgetex(&mut self, key: &[u8], args: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let options = args.iter().position(|x| x.eq_ignore_ascii_case(b"fields")).unwrap_or(args.len());
+        let fields = hash_fields(args, options, 1)?;
+        let expire = match &args[..options] {
+            [] => None,
+            [x] if x.eq_ignore_ascii_case(b"persist") => Some(None),
+            [unit, ttl] if unit.eq_ignore_ascii_case(b"ex") => Some(Some(deadline(ttl, 1000, "hgetex")?)),
+            [unit, ttl] if unit.eq_ignore_ascii_case(b"px") => Some(Some(deadline(ttl, 1, "hgetex")?)),
+            _ => return Err(Reply::syntax())
+        };
+
+        let values: Vec<_> = match self.hash_mut(key, false)? {
+            Some(hash) => fields.iter().map(|field| hash.get(field).cloned()).collect(),
+            None => vec![None; fields.len()]
+        };
+        if let Some(expire) = expire {
+            for (field, _) in fields.iter().zip(&values).filter(|(_, value)| value.is_some()) {
+                match expire {
+                    Some(at) if at <= Instant::now() => {
+                        self.hash_mut(key, false)?.unwrap().remove(field);
+                        self.persist_field(key, field);
+                    },
+                    Some(at) => self.field_expires.entry(key.to_vec()).or_default().insert(field.clone(), at).ignore(),
+                    None => self.persist_field(key, field).ignore()
+                }
+            }
+            self.cleanup(key);
+            self.touch(key)
+        }
+        Ok(Reply::Array(Some(values.into_iter().map(Reply::Bulk).collect())))
+    }
+
+    fn ttl(&mut self, key: &[u8]) -> Result<i64, Reply> {
+        self.expire(key);
+        Ok(match self.entries.get(key) {
+            Some((_, Some(at))) => at.saturating_duration_since(Instant::now()).as_millis() as _,
+            Some((_, None)) => -1,
+            None => -2
+        })
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8], options: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let (mut nx, mut xx, mut expire) = (false, false, None);
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            match &option.to_ascii_lowercase()[..] {
+                b"nx" => nx = true,
+                b"xx" => xx = true,
+                b"ex" => expire = Some(deadline(options.next().ok_or_else(Reply::syntax)?, 1000, "set")?),
+                b"px" => expire = Some(deadline(options.next().ok_or_else(Reply::syntax)?, 1, "set")?),
+                _ => return Err(Reply::syntax())
+            }
+        }
+
+        self.expire(key);
+        let exists = self.entries.contains_key(key);
+        if (nx && exists) || (xx && !exists) {
+            return Ok(Reply::Bulk(None))
+        }
+        self.entries.insert(key.to_vec(), (Value::String(value.to_vec()), expire));
+        self.touch(key);
+        Ok(Reply::ok())
+    }
+
+    fn bitcount(&mut self, key: &[u8], range: &[Vec<u8>]) -> Result<Reply, Reply> {
+        if range.len() == 1 || range.len() > 3 {
+            return Err(Reply::syntax())
+        }
+        let x = self.string_mut(key, false)?.cloned().unwrap_or_default();
+        let range = bit_range(&x, range)?;
+        Ok(Reply::Integer(range.map(|(start, end)| (start..=end).filter(|&i| get_bit(&x, i)).count()).unwrap_or(0) as _))
+    }
+
+    fn bitpos(&mut self, key: &[u8], bit: &[u8], range: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let bit = match bit {
+            b"0" => false,
+            b"1" => true,
+            _ => return Err(Reply::Error("ERR The bit argument must be 1 or 0.".into()))
+        };
+        if range.len() > 3 {
+            return Err(Reply::syntax())
+        }
+        self.expire(key);
+        if !self.entries.contains_key(key) {
+            return Ok(Reply::Integer(if bit { -1 } else { 0 }))
+        }
+
+        let x = self.string_mut(key, false)?.cloned().unwrap_or_default();
+        let bit_range = bit_range(&x, range)?;
+        let found = bit_range.and_then(|(start, end)| (start..=end).find(|&i| get_bit(&x, i) == bit));
+        Ok(Reply::Integer(match found {
+            Some(i) => i as _,
+            // looking for 0 in a string of 1s without an explicit end: the string is considered padded with 0
+            None if !bit && range.len() < 2 && bit_range.is_some() => x.len() as i64 * 8,
+            None => -1
+        }))
+    }
+
+    fn bitop(&mut self, op: &[u8], dest: &[u8], keys: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let op = op.to_ascii_lowercase();
+        let sources = keys.iter().map(|key| Ok(self.string_mut(key, false)?.cloned().unwrap_or_default())).collect::<Result<Vec<_>, Reply>>()?;
+        let len = sources.iter().map(|x| x.len()).max().unwrap_or(0);
+        let byte = |x: &Vec<u8>, i: usize| x.get(i).cloned().unwrap_or(0);
+        let result: Vec<u8> = match &op[..] {
+            b"not" if sources.len() == 1 => sources[0].iter().map(|x| !x).collect(),
+            b"not" => return Err(Reply::Error("ERR BITOP NOT must be called with a single source key.".into())),
+            b"and" => (0..len).map(|i| sources.iter().fold(0xff, |acc, x| acc & byte(x, i))).collect(),
+            b"or" => (0..len).map(|i| sources.iter().fold(0, |acc, x| acc | byte(x, i))).collect(),
+            b"xor" => (0..len).map(|i| sources.iter().fold(0, |acc, x| acc ^ byte(x, i))).collect(),
+            _ => return Err(Reply::syntax())
+        };
+
+        let len = result.len();
+        if result.is_empty() {
+            self.entries.remove(dest);
+        } else {
+            self.entries.insert(dest.to_vec(), (Value::String(result), None));
+        }
+        self.touch(dest);
+        Ok(Reply::Integer(len as _))
+    }
+
+    fn bitfield(&mut self, key: &[u8], ops: &[Vec<u8>]) -> Result<Reply, Reply> {
+        let mut x = self.string_mut(key, false)?.cloned().unwrap_or_default();
+        let mut overflow = b"wrap".to_vec();
+        let mut replies = vec![];
+        let mut modified = false;
+        let mut ops = ops.iter();
+
+        while let Some(op) = ops.next() {
+            let op = op.to_ascii_lowercase();
+            if op == b"overflow" {
+                overflow = ops.next().ok_or_else(Reply::syntax)?.to_ascii_lowercase();
+                if ![&b"wrap"[..], b"sat", b"fail"].contains(&&overflow[..]) {
+                    return Err(Reply::Error("ERR Invalid OVERFLOW type specified".into()))
+                }
+                continue
+            }
+
+            let ty = ops.next().ok_or_else(Reply::syntax)?;
+            let signed = match ty.first() {
+                Some(b'i') => true,
+                Some(b'u') => false,
+                _ => return Err(Reply::Error("ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.".into()))
+            };
+            let bits: usize = parse_number(&ty[1..])?;
+            if bits == 0 || bits > 64 || (!signed && bits == 64) {
+                return Err(Reply::Error("ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.".into()))
+            }
+            let offset = ops.next().ok_or_else(Reply::syntax)?;
+            let offset = match offset.first() {
+                Some(b'#') => parse_number::<usize>(&offset[1..])?.checked_mul(bits).filter(|&x| x < MAX_BITS).ok_or_else(Reply::bit_offset)?,
+                _ => parse_offset(offset)?
+            };
+            let (min, max) = if signed { (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1) } else { (0, (1i128 << bits) - 1) };
+
+            let old = get_int(&x, offset, bits, signed);
+            let target = match &op[..] {
+                b"get" => {
+                    replies.push(Reply::Integer(old as _));
+                    continue
+                },
+                b"set" => parse_number::<i64>(ops.next().ok_or_else(Reply::syntax)?)? as i128,
+                b"incrby" => old + parse_number::<i64>(ops.next().ok_or_else(Reply::syntax)?)? as i128,
+                _ => return Err(Reply::syntax())
+            };
+            let new = if target >= min && target <= max {
+                Some(target)
+            } else {
+                match &overflow[..] {
+                    b"wrap" => {
+                        let wrapped = target.rem_euclid(1i128 << bits);
+                        Some(if signed && wrapped > max { wrapped - (1i128 << bits) } else { wrapped })
+                    },
+                    b"sat" => Some(target.clamp(min, max)),
+                    _ => None
+                }
+            };
+
+            match new {
+                Some(new) => {
+                    set_int(&mut x, offset, bits, new);
+                    modified = true;
+                    replies.push(Reply::Integer(if op == b"set" { old } else { new } as _))
+                },
+                None => replies.push(Reply::Bulk(None))
+            }
+        }
+
+        if modified {
+            let expire = self.entries.get(key).and_then(|(_, expire)| *expire);
+            self.entries.insert(key.to_vec(), (Value::String(x), expire));
+            self.touch(key)
+        }
+        Ok(Reply::Array(Some(replies)))
+    }
+}
+
+// parse the optional `start end [BYTE|BIT]` arguments into a bit range of `x`
+fn bit_range(x: &[u8], range: &[Vec<u8>]) -> Result<Option<(usize, usize)>, Reply> {
+    let bits = match range.get(2).map(|x| x.to_ascii_lowercase()) {
+        None => false,
+        Some(unit) if unit == b"byte" => false,
+        Some(unit) if unit == b"bit" => true,
+        _ => return Err(Reply::syntax())
+    };
+    let len = if bits { x.len() * 8 } else { x.len() };
+    let (start, end) = match range {
+        [] => (0, -1),
+        [start] => (parse_number(start)?, -1),
+        [start, end, ..] => (parse_number(start)?, parse_number(end)?)
+    };
+    Ok(normalize_range(start, end, len).map(|(start, end)| if bits { (start, end) } else { (start * 8, end * 8 + 7) }))
+}
+
+fn get_bit(x: &[u8], i: usize) -> bool {
+    x.get(i / 8).map(|byte| byte & (0x80 >> (i % 8)) != 0).unwrap_or(false)
+}
+
+fn set_bit(x: &mut Vec<u8>, i: usize, value: bool) {
+    if x.len() <= i / 8 {
+        x.resize(i / 8 + 1, 0)
+    }
+    if value {
+        x[i / 8] |= 0x80 >> (i % 8)
+    } else {
+        x[i / 8] &= !(0x80 >> (i % 8))
+    }
+}
+
+fn get_int(x: &[u8], offset: usize, bits: usize, signed: bool) -> i128 {
+    let value = (0..bits).fold(0i128, |acc, i| (acc << 1) | get_bit(x, offset + i) as i128);
+    if signed && get_bit(x, offset) {
+        value - (1i128 << bits)
+    } else {
+        value
+    }
+}
+
+fn set_int(x: &mut Vec<u8>, offset: usize, bits: usize, value: i128) {
+    for i in 0..bits {
+        set_bit(x, offset + i, (value >> (bits - 1 - i)) & 1 == 1)
+    }
+}
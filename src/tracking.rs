@@ -0,0 +1,282 @@
+use crate::*;
+use crate::cache::Lru;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// read commands whose replies are cached. They all take a single key as the first argument.
+const CACHEABLE: &[&[u8]] = &[
+    b"get", b"strlen", b"getrange", b"getbit", b"bitcount", b"bitpos",
+    b"hget", b"hmget", b"hgetall", b"hlen", b"hexists", b"hkeys", b"hvals", b"hstrlen",
+    b"lindex", b"llen", b"lrange",
+    b"scard", b"smembers", b"sismember",
+    b"zcard", b"zscore", b"zrange",
+    b"geopos", b"geodist"
+];
+
+/// ClientCache keeps replies of read commands sent through `Tracked` connections, and evicts them when Redis reports
+/// that the keys are modified, using CLIENT TRACKING redirected to a dedicated connection. Requires Redis 6.0.
+#[derive(Clone)]
+pub struct ClientCache {
+    state: Arc<Mutex<CacheState>>,
+    redirect: i64
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackingMode {
+    /// Redis remembers the keys read by each connection and only reports modifications of them
+    Default,
+    /// Redis reports modifications of all keys starting with any of the prefixes, and does not need to remember anything
+    Broadcast(Vec<Box<[u8]>>)
+}
+
+struct CacheState {
+    replies: Lru<Cached>, // command -> reply
+    commands: HashMap<Box<[u8]>, HashSet<Box<[u8]>>>, // key -> commands
+    inflight: HashMap<Box<[u8]>, (usize, bool)>, // key -> (number of requests waiting for reply, invalidated)
+}
+
+#[derive(Clone)]
+struct Cached {
+    reply: Arc<[u8]>,
+    key: Box<[u8]>
+}
+
+impl CacheState {
+    fn invalidate(&mut self, key: &[u8]) {
+        for command in self.commands.remove(key).unwrap_or_default() {
+            self.replies.remove(&command);
+        }
+        if let Some((_, invalidated)) = self.inflight.get_mut(key) {
+            *invalidated = true
+        }
+    }
+
+    fn clear(&mut self) {
+        self.replies.clear();
+        self.commands.clear();
+        for (_, invalidated) in self.inflight.values_mut() {
+            *invalidated = true
+        }
+    }
+
+    fn insert(&mut self, command: Box<[u8]>, key: Box<[u8]>, reply: Arc<[u8]>) {
+        self.commands.entry(key.clone()).or_default().insert(command.clone());
+        for (command, evicted) in self.replies.insert(command, Cached { reply, key }, None) {
+            if let Some(commands) = self.commands.get_mut(&evicted.key) {
+                commands.remove(&command);
+                if commands.is_empty() {
+                    self.commands.remove(&evicted.key);
+                }
+            }
+        }
+    }
+}
+
+impl ClientCache {
+    /// create a cache of at most `capacity` replies. `conn` is a dedicated connection that is moved into a background
+    /// thread receiving invalidations, which exits when all clones of the ClientCache are dropped and the next message arrives.
+    pub fn new<T: Read + Write + Send + 'static>(capacity: usize, mut conn: T) -> Result<Self, RedisError> {
+        let redirect = Session::new(&mut conn).arg(b"client").arg(b"id").fetch()?.integer();
+        Session::new(&mut conn).arg(b"subscribe").arg(b"__redis__:invalidate").fetch()?.ignore();
+
+        let state = Arc::new(Mutex::new(CacheState { replies: Lru::new(capacity), commands: HashMap::new(), inflight: HashMap::new() }));
+        let weak = Arc::downgrade(&state);
+        listen_invalidations(conn, move |keys| match weak.upgrade() {
+            Some(state) => {
+                let mut state = state.lock().unwrap();
+                match keys {
+                    Some(keys) => for key in keys {
+                        state.invalidate(&key)
+                    },
+                    None => state.clear()
+                }
+                true
+            },
+            None => false
+        });
+
+        Ok(Self { state, redirect })
+    }
+
+    /// enable tracking on `conn` and wrap it so read commands may be served from this cache.
+    pub fn track<T: Read + Write>(&self, mut conn: T, mode: &TrackingMode) -> Result<Tracked<T>, RedisError> {
+        let mut sess = Session::new(&mut conn);
+        sess.arg(b"client").arg(b"tracking").arg(b"on").arg(b"redirect").arg(self.redirect);
+        if let TrackingMode::Broadcast(prefixes) = mode {
+            sess.arg(b"bcast");
+            for prefix in prefixes {
+                sess.arg(b"prefix").arg(prefix);
+            }
+        }
+        sess.fetch()?.ignore();
+        drop(sess);
+
+        Ok(Tracked { inner: conn, state: self.state.clone(), written: vec![], received: vec![], pending: VecDeque::new(), reply: vec![], pos: 0, bypass: false })
+    }
+
+    /// the number of cached replies
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().replies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.state.lock().unwrap().clear()
+    }
+}
+
+enum Pending {
+    Hit(Arc<[u8]>),
+    /// the command and key if the reply should be cached
+    Miss(Option<Request>)
+}
+
+struct Request {
+    command: Box<[u8]>,
+    key: Box<[u8]>
+}
+
+/// A connection with client side caching. It works with any AsRedis implementation for connections, like `&mut T` and `Pool`.
+pub struct Tracked<T> {
+    inner: T,
+    state: Arc<Mutex<CacheState>>,
+    written: Vec<u8>, // incomplete command
+    received: Vec<u8>, // incomplete reply
+    pending: VecDeque<Pending>,
+    reply: Vec<u8>, // the reply being read
+    pos: usize,
+    bypass: bool // after WATCH or MULTI, where reads must reach the server
+}
+
+impl<T: Read + Write> Tracked<T> {
+    fn dispatch(&mut self, command: Vec<u8>, args: Vec<(usize, usize)>) -> std::io::Result<()> {
+        let args: Vec<&[u8]> = args.into_iter().map(|(start, end)| &command[start..end]).collect();
+        let mut state = self.state.lock().unwrap();
+
+        let name = args.first().map(|x| x.to_ascii_lowercase()).unwrap_or_default();
+        match &name[..] {
+            b"watch" | b"multi" => self.bypass = true,
+            b"unwatch" | b"exec" | b"discard" => self.bypass = false,
+            _ => {}
+        }
+
+        match &args[..] {
+            // reads in a transaction must reach the server, and their replies are `QUEUED` instead of the values.
+            // after WATCH a cached value may be older than the watched one, so a transaction based on it could succeed.
+            [_, _, ..] if self.bypass && CACHEABLE.contains(&&name[..]) => self.pending.push_back(Pending::Miss(None)),
+            [_, key, ..] if CACHEABLE.contains(&&name[..]) => {
+                if let Some(cached) = state.replies.get(&command) {
+                    self.pending.push_back(Pending::Hit(cached.reply));
+                    return Ok(())
+                }
+                let key: Box<[u8]> = (*key).into();
+                state.inflight.entry(key.clone()).or_insert((0, false)).0 += 1;
+                self.pending.push_back(Pending::Miss(Some(Request { command: command[..].into(), key })))
+            },
+            _ => {
+                // the invalidation of our own writes may arrive after our next read, so evict the keys now.
+                // we don't know which arguments are keys, so just try all of them.
+                for arg in args.iter().skip(1) {
+                    state.invalidate(arg)
+                }
+                self.pending.push_back(Pending::Miss(None))
+            }
+        }
+
+        drop(state);
+        self.inner.write_all(&command)
+    }
+
+    // read a whole reply from the inner connection
+    fn receive(&mut self) -> std::io::Result<Vec<u8>> {
+        loop {
+            if let Some(len) = resp_len(&self.received) {
+                let rest = self.received.split_off(len);
+                return Ok(std::mem::replace(&mut self.received, rest))
+            }
+
+            let mut buf = [0; 4096];
+            let n = self.inner.read(&mut buf)?;
+            if n == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into())
+            }
+            self.received.extend_from_slice(&buf[..n])
+        }
+    }
+}
+
+impl<T: Read + Write> Write for Tracked<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        while let Some((len, args)) = parse_command(&self.written) {
+            let rest = self.written.split_off(len);
+            let command = std::mem::replace(&mut self.written, rest);
+            self.dispatch(command, args)?
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T: Read + Write> Read for Tracked<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.reply.len() {
+            self.pos = 0;
+            self.reply = match self.pending.pop_front() {
+                Some(Pending::Hit(reply)) => reply.to_vec(),
+                Some(Pending::Miss(cache)) => {
+                    let reply = self.receive();
+                    if let Some(Request { command, key }) = cache {
+                        let mut state = self.state.lock().unwrap();
+                        let (waiting, invalidated) = state.inflight.get_mut(&key).unwrap();
+                        let invalidated = *invalidated;
+                        *waiting -= 1;
+                        if *waiting == 0 {
+                            state.inflight.remove(&key);
+                        }
+                        match &reply {
+                            Ok(reply) if !invalidated && matches!(reply[0], b'$' | b':' | b'*') => state.insert(command, key, reply[..].into()),
+                            _ => {}
+                        }
+                    }
+                    reply?
+                },
+                // not a reply of a command, e.g. messages of subscriptions
+                None => if self.received.is_empty() {
+                    return self.inner.read(buf)
+                } else {
+                    std::mem::take(&mut self.received)
+                }
+            }
+        }
+
+        let n = buf.len().min(self.reply.len() - self.pos);
+        buf[..n].copy_from_slice(&self.reply[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// receive invalidation messages from a subscribed connection in a background thread, until the connection fails or `f`
+/// returns false. `f` receives the invalidated keys, or None if all keys should be invalidated.
+pub(crate) fn listen_invalidations<T: Read + Send + 'static>(mut conn: T, mut f: impl FnMut(Option<Vec<Box<[u8]>>>) -> bool + Send + 'static) {
+    std::thread::spawn(move || {
+        let mut parser = RespParser::default();
+        while let Ok(Response::List(mut msg)) = parser.read_reply(&mut conn) {
+            let keys = match msg.pop() {
+                Some(Response::Bytes(key)) => Some(vec![(*key).into()]), // published by writers
+                Some(Response::List(keys)) => Some(keys.into_iter().map(|x| (*x.bytes()).into()).collect()), // tracking may invalidate multiple keys at once
+                Some(Response::Nothing) => None, // tracking sends null when the database is flushed
+                _ => continue
+            };
+            if !f(keys) {
+                return
+            }
+        }
+    });
+}
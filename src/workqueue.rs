@@ -0,0 +1,194 @@
+use crate::*;
+use std::borrow::Borrow;
+use std::time::{Duration, Instant};
+
+// KEYS: processing, pending, retries, dead. ARGV: item, max retries
+static FAIL: Script = Script::from_static("
+if redis.call('lrem', KEYS[1], 1, ARGV[1]) == 0 then
+    return 0
+end
+if redis.call('hincrby', KEYS[3], ARGV[1], 1) > tonumber(ARGV[2]) then
+    redis.call('hdel', KEYS[3], ARGV[1])
+    redis.call('lpush', KEYS[4], ARGV[1])
+else
+    redis.call('lpush', KEYS[2], ARGV[1])
+end
+return 1");
+
+// KEYS: alive, processing, pending, retries, dead, consumers. ARGV: consumer, max retries
+static RECOVER: Script = Script::from_static("
+if redis.call('exists', KEYS[1]) == 1 then
+    return 0
+end
+local count = 0
+while true do
+    local item = redis.call('rpop', KEYS[2])
+    if not item then
+        break
+    end
+    if redis.call('hincrby', KEYS[4], item, 1) > tonumber(ARGV[2]) then
+        redis.call('hdel', KEYS[4], item)
+        redis.call('lpush', KEYS[5], item)
+    else
+        redis.call('rpush', KEYS[3], item)
+    end
+    count = count + 1
+end
+redis.call('srem', KEYS[6], ARGV[1])
+return count");
+
+/// WorkQueue is a FIFO queue where received items are kept in a per-consumer processing list until acknowledged.
+/// Items of consumers that stop sending heartbeats for `visibility_timeout` can be re-queued by any consumer with `recover`.
+/// Items failed more than `max_retries` times are moved to a dead letter list. Retries are counted by the serialized item,
+/// so equal items share the same counter.
+pub struct WorkQueue<A, C, K, T>
+{
+    client: C,
+    key: K,
+    consumer: String,
+    processing: Box<[u8]>,
+    alive: Box<[u8]>,
+    retries: Box<[u8]>,
+    dead: Box<[u8]>,
+    consumers: Box<[u8]>,
+    visibility_timeout: Duration,
+    max_retries: u64,
+    serializer: fn(x: &T) -> Box<[u8]>,
+    deserializer: fn(x: &[u8]) -> T,
+    phantom: std::marker::PhantomData<A>
+}
+
+/// An item received from a WorkQueue, which should be passed to `ack` or `fail` after processing.
+#[derive(Debug, Clone)]
+pub struct Job<T> {
+    pub item: T,
+    raw: Bytes
+}
+
+impl<A, C: Deref<Target=A>, K: Borrow<[u8]>, T> WorkQueue<A, C, K, T> where for<'a> &'a A: AsRedis {
+    /// `consumer` must be unique among all consumers of the queue and stable across restarts of the same consumer.
+    pub fn new(client: C, key: K, consumer: impl Into<String>, serializer: fn(x: &T) -> Box<[u8]>, deserializer: fn(x: &[u8]) -> T) -> Self {
+        let consumer = consumer.into();
+        let derive = |suffix: &[u8]| [key.borrow(), suffix].concat().into_boxed_slice();
+        let processing = derive(&[b":processing:", consumer.as_bytes()].concat());
+        let alive = derive(&[b":alive:", consumer.as_bytes()].concat());
+        let (retries, dead, consumers) = (derive(b":retries"), derive(b":dead"), derive(b":consumers"));
+        Self {
+            client, key, consumer, processing, alive, retries, dead, consumers,
+            visibility_timeout: Duration::from_secs(30), max_retries: 3,
+            serializer, deserializer, phantom: std::marker::PhantomData
+        }
+    }
+
+    /// default to 30 seconds
+    pub fn visibility_timeout(mut self, timeout: Duration) -> Self {
+        self.visibility_timeout = timeout;
+        self
+    }
+
+    /// default to 3
+    pub fn max_retries(mut self, n: u64) -> Self {
+        self.max_retries = n;
+        self
+    }
+
+    fn initiate(&self, cmd: &[u8]) -> Session<<&A as AsRedis>::P> {
+        self.client.arg(cmd).apply(|x| x.arg(self.key.borrow()).ignore())
+    }
+
+    /// remove all items including the processing list of this consumer, the retry counters and dead letters.
+    pub fn clear(&self) -> Result<(), RedisError> {
+        self.initiate(b"del").arg(&self.processing).arg(&self.retries).arg(&self.dead).fetch().map(|x| x.ignore())
+    }
+
+    pub fn push(&self, x: impl Borrow<T>) -> Result<(), RedisError> {
+        self.initiate(b"lpush").arg((self.serializer)(x.borrow())).fetch().map(|x| x.ignore())
+    }
+
+    /// the number of pending items, not including the ones being processed
+    pub fn len(&self) -> Result<usize, RedisError> {
+        self.initiate(b"llen").fetch().map(|x| x.integer() as _)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, RedisError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// tell others that this consumer is still alive. Must be called more frequently than `visibility_timeout`
+    /// if processing a job takes long. `recv` also sends a heartbeat.
+    pub fn heartbeat(&self) -> Result<(), RedisError> {
+        self.client.arg(b"set").arg(&self.alive).arg(b"1")
+            .arg(b"px").arg(self.visibility_timeout.as_millis())
+            .fetch()?.ignore();
+        self.client.arg(b"sadd").arg(&self.consumers).arg(self.consumer.as_bytes()).fetch().map(|x| x.ignore())
+    }
+
+    /// blocking receive a job and move it into the processing list. Return None when timeout reached.
+    /// Zero timeout means waiting indefinitely. Requires Redis 6.2.
+    pub fn recv(&self, timeout: Duration) -> Result<Option<Job<T>>, RedisError> {
+        // block in slices shorter than the visibility timeout with a heartbeat before each, so others do not recover
+        // the jobs of this consumer while it is waiting
+        let slice = (self.visibility_timeout / 2).max(Duration::from_millis(1));
+        let deadline = Some(timeout).filter(|x| !x.is_zero()).map(|x| Instant::now() + x);
+        loop {
+            self.heartbeat()?;
+            let wait = deadline.map_or(slice, |x| slice.min(x.saturating_duration_since(Instant::now())));
+            match self.initiate(b"blmove").arg(&self.processing).arg(b"right").arg(b"left")
+                .arg(wait.max(Duration::from_millis(1)).as_secs_f64())
+                .fetch()? {
+                Response::Bytes(raw) => return Ok(Some(Job { item: (self.deserializer)(&raw), raw })),
+                Response::Nothing => if deadline.is_some_and(|x| Instant::now() >= x) {
+                    return Ok(None)
+                },
+                _ => unreachable!()
+            }
+        }
+    }
+
+    /// mark the job as done and remove it from the processing list
+    pub fn ack(&self, job: &Job<T>) -> Result<(), RedisError> {
+        self.client.arg(b"lrem").arg(&self.processing).arg(b"1").arg(&job.raw).fetch()?.ignore();
+        self.client.arg(b"hdel").arg(&self.retries).arg(&job.raw).fetch().map(|x| x.ignore())
+    }
+
+    /// give the job back to the queue, or move it to the dead letter list if it has failed too many times.
+    pub fn fail(&self, job: &Job<T>) -> Result<(), RedisError> {
+        let keys = [&self.processing, self.key.borrow(), &self.retries, &self.dead];
+        self.invoke(&FAIL, &keys, &[&job.raw, &self.max_retries])
+    }
+
+    /// re-queue the jobs held by consumers whose heartbeats are missing for `visibility_timeout`,
+    /// counting as a failure of each job. Return the number of recovered jobs.
+    pub fn recover(&self) -> Result<usize, RedisError> {
+        let consumers = self.client.arg(b"smembers").arg(&self.consumers).fetch()?.list();
+        let mut count = 0;
+        for consumer in consumers {
+            let consumer = consumer.bytes();
+            let processing = [self.key.borrow(), b":processing:", &consumer].concat();
+            let alive = [self.key.borrow(), b":alive:", &consumer].concat();
+            let keys = [&alive[..], &processing, self.key.borrow(), &self.retries, &self.dead, &self.consumers];
+            count += self.invoke::<i64>(&RECOVER, &keys, &[&consumer, &self.max_retries])? as usize;
+        }
+        Ok(count)
+    }
+
+    fn invoke<R: FromResponse>(&self, script: &Script, keys: &[&[u8]], args: &[&dyn ToArg]) -> Result<R, RedisError> {
+        let keys: Vec<_> = keys.iter().map(|x| RawKey(x)).collect();
+        let keys: Vec<_> = keys.iter().map(|x| x as &dyn Collection).collect();
+        script.invoke(&*self.client, &keys, args)
+    }
+
+    /// the items that failed more than `max_retries` times, newest first
+    pub fn dead_letters(&self) -> Result<Vec<T>, RedisError> {
+        Ok(self.client.arg(b"lrange").arg(&self.dead).arg(b"0").arg(b"-1")
+            .fetch()?.list().into_iter()
+            .map(|x| (self.deserializer)(&x.bytes()))
+            .collect())
+    }
+}
+
+impl<A, C, K: Borrow<[u8]>, T> Collection for WorkQueue<A, C, K, T> {
+    fn key(&self) -> &[u8] {
+        self.key.borrow()
+    }
+}
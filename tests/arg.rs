@@ -0,0 +1,21 @@
+use redis_alchemy::*;
+use redis_alchemy::testing::MockConnection;
+use std::cell::RefCell;
+use std::time::Duration;
+
+#[test]
+fn arg_encoding() {
+    let mut mock = MockConnection::new();
+    mock.expect(&[b"zadd", b"z", b"1.5", b"a", b"-3", b"b"]).reply(Response::Integer(2))
+        .expect(&[b"set", b"k", b"v", b"px", b"1500"]).reply(Response::Text("OK".into()))
+        .expect(&[b"x", b"1e300", b"inf", b"-7", b"18446744073709551615", b"s", b"t"]).reply(Response::Nothing)
+        .expect(&[b"y", b"a", b"b", b"c", b"d"]).reply(Response::Nothing);
+    let conn = RefCell::new(mock);
+
+    assert_eq!(conn.arg("zadd").arg(&b"z"[..]).arg(1.5).arg("a").arg(-3).arg(b"b").fetch().unwrap().integer(), 2);
+    let ttl: Option<Duration> = Some(Duration::from_millis(1500));
+    assert_eq!(conn.arg(b"set").arg("k").arg(String::from("v")).arg(ttl.map(|_| "px")).arg(ttl).fetch().unwrap().text(), "OK");
+    assert!(conn.arg(b"x").arg(1e300).arg(f64::INFINITY).arg(-7i8).arg(u64::MAX).arg(None::<i32>).arg(["s", "t"]).fetch().unwrap().is_nothing());
+    assert!(conn.arg(b"y").arg(vec![("a", b"b"), ("c", b"d")]).fetch().unwrap().is_nothing());
+    conn.borrow().verify();
+}
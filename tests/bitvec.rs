@@ -13,3 +13,64 @@ fn bitvec() {
     bitvec.set(2, false).unwrap();
     assert!(bitvec.find_first().unwrap().is_none());
 }
+
+#[test]
+fn bitvec_ops() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let a = BitVec::new(&client, &b"bitvec_ops_a"[..]);
+    let b = BitVec::new(&client, &b"bitvec_ops_b"[..]);
+    let c = BitVec::new(&client, &b"bitvec_ops_c"[..]);
+    a.set_raw(&[0b1100_0000, 0b0000_0001]).unwrap();
+    b.set_raw(&[0b1010_0000]).unwrap();
+
+    assert_eq!(a.sum_range(0..=0, BitUnit::Byte).unwrap(), 2);
+    assert_eq!(a.sum_range(1..=15, BitUnit::Bit).unwrap(), 2);
+    assert_eq!(a.find(true, 1.., BitUnit::Byte).unwrap(), Some(15));
+    assert_eq!(a.find(false, 0..=1, BitUnit::Bit).unwrap(), None);
+    assert_eq!(a.sum_range(1..1, BitUnit::Byte).unwrap(), 0);
+
+    assert_eq!(a.and(&[&b], &c).unwrap(), 2);
+    assert_eq!(&c.get_raw().unwrap()[..], &[0b1000_0000, 0]);
+    a.or(&[&b], &c).unwrap();
+    assert_eq!(&c.get_raw().unwrap()[..], &[0b1110_0000, 1]);
+    a.xor(&[&b], &c).unwrap();
+    assert_eq!(&c.get_raw().unwrap()[..], &[0b0110_0000, 1]);
+    b.not(&c).unwrap();
+    assert_eq!(&c.get_raw().unwrap()[..], &[0b0101_1111]);
+}
+
+#[test]
+fn bitvec_bitfield() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let bitvec = BitVec::new(&client, &b"bitvec_bitfield"[..]);
+    bitvec.clear().unwrap();
+
+    let field = bitvec.bitfield(BitFieldType::Unsigned(8));
+    assert_eq!(field.set(1, 200).unwrap(), Some(0));
+    assert_eq!(field.get(1).unwrap(), 200);
+    assert_eq!(field.increment(1, 100).unwrap(), Some(44));
+    let field = field.overflow(Overflow::Sat);
+    assert_eq!(field.increment(1, 300).unwrap(), Some(255));
+    let field = field.overflow(Overflow::Fail);
+    assert_eq!(field.increment(1, 1).unwrap(), None);
+    assert_eq!(bitvec.bitfield(BitFieldType::Signed(4)).get(2).unwrap(), -1);
+}
+
+#[test]
+fn bitvec_bulk() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let bitvec = BitVec::new(&client, &b"bitvec_bulk"[..]);
+    bitvec.clear().unwrap();
+    assert!(bitvec.get_raw().unwrap().is_empty());
+
+    bitvec.set_many(&[1, 3, 5, 1000], true).unwrap();
+    bitvec.set_many(&[3], false).unwrap();
+    assert_eq!(bitvec.ones().unwrap().collect::<Vec<_>>(), vec![1, 5, 1000]);
+    assert_eq!(bitvec.sum().unwrap(), 3);
+
+    let bits = [true, false, false, true, true, false, false, false, false, true];
+    bitvec.from_bits(&bits).unwrap();
+    let bitset = bitvec.to_bitset().unwrap();
+    assert_eq!(bitset.len(), 16);
+    assert_eq!(&bitset[..10], &bits[..]);
+}
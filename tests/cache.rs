@@ -0,0 +1,63 @@
+use redis_alchemy::*;
+use std::net::TcpStream;
+use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn new_cache<'c>(client: &'c TcpClient<&'static str>, prefix: &'static [u8]) -> Cache<TcpClient<&'static str>, &'c TcpClient<&'static str>, &'static [u8], String, i32> {
+    Cache::new(client, prefix, Duration::from_secs(10),
+        |x: &String| x.as_bytes().into(),
+        |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    )
+}
+
+#[test]
+fn cache() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let cache = new_cache(&client, b"cache:");
+    cache.remove("a".to_string()).unwrap();
+
+    assert_eq!(cache.get("a".to_string()).unwrap(), None);
+    assert_eq!(cache.get_or_insert_with(&"a".to_string(), || 1).unwrap(), 1);
+    assert_eq!(cache.get_or_insert_with(&"a".to_string(), || 2).unwrap(), 1);
+    cache.insert("a".to_string(), 3).unwrap();
+    assert_eq!(cache.get("a".to_string()).unwrap(), Some(3));
+}
+
+#[test]
+fn cache_single_flight() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let cache = new_cache(&client, b"cache_single_flight:");
+    cache.remove("a".to_string()).unwrap();
+
+    let calls = AtomicUsize::new(0);
+    let handles: Vec<_> = (0..8).map(|_| oh_my_rust::scoped_spawn(|| {
+        let v = cache.get_or_insert_with(&"a".to_string(), || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(200));
+            42
+        }).unwrap();
+        assert_eq!(v, 42);
+    })).collect();
+    for handle in handles {
+        handle.join().unwrap()
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn cache_local() {
+    for (prefix, mode) in [(&b"cache_local_pubsub:"[..], Invalidation::PubSub), (&b"cache_local_tracking:"[..], Invalidation::Tracking)] {
+        let client = TcpClient::new("127.0.0.1:6379");
+        let local = new_cache(&client, prefix).with_local(16, TcpStream::connect("127.0.0.1:6379").unwrap(), mode).unwrap();
+        let remote = new_cache(&client, prefix).publish_invalidations();
+        remote.insert("a".to_string(), 1).unwrap();
+
+        assert_eq!(local.get("a".to_string()).unwrap(), Some(1));
+        remote.insert("a".to_string(), 2).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(local.get("a".to_string()).unwrap(), Some(2));
+        remote.remove("a".to_string()).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(local.get("a".to_string()).unwrap(), None);
+    }
+}
@@ -1,4 +1,8 @@
 use redis_alchemy::*;
+use std::cell::RefCell;
+use std::io::Write;
+use std::net::TcpStream;
+use std::time::Duration;
 
 #[test]
 fn cell() {
@@ -7,3 +11,84 @@ fn cell() {
     cell.set("yes".to_string()).unwrap();
     assert_eq!(&cell.get().unwrap()[..], "yes")
 }
+
+#[test]
+fn cell_conditional() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let cell = Cell::new(&client, &b"cell_conditional"[..], |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap());
+    cell.clear().unwrap();
+
+    assert!(cell.set_if_absent("a".to_string(), None).unwrap());
+    assert!(!cell.set_if_absent("b".to_string(), Some(Duration::from_secs(10))).unwrap());
+    assert!(cell.set_if_absent("b".to_string(), Some(Duration::from_micros(10))).is_err());
+    assert!(!cell.expire_if_eq("b".to_string(), Duration::from_millis(100)).unwrap());
+    assert!(cell.expire_if_eq("a".to_string(), Duration::from_millis(100)).unwrap());
+    assert!(!cell.remove_if_eq("b".to_string()).unwrap());
+    assert!(cell.remove_if_eq("a".to_string()).unwrap());
+    assert!(!cell.remove_if_eq("a".to_string()).unwrap());
+}
+
+#[test]
+fn cell_stream() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let cell = Cell::new(&client, &b"cell_stream"[..], |x: &Vec<u8>| x.clone().into(), |x| x.to_vec());
+    let blob: Vec<u8> = (0..1_000_000).map(|i| (i % 251) as u8).collect();
+    cell.set_from_reader(&mut &blob[..], blob.len() as _).unwrap();
+    assert!(cell.set_from_reader(&mut &blob[..10], 20).is_err());
+
+    let mut buf = vec![];
+    assert_eq!(cell.get_to_writer(&mut buf).unwrap(), Some(blob.len()));
+    assert!(buf == blob);
+    assert_eq!(cell.get().unwrap().len(), blob.len());
+
+    cell.clear().unwrap();
+    assert_eq!(cell.get_to_writer(&mut buf).unwrap(), None);
+}
+
+#[test]
+fn cell_stream_short_read() {
+    // the command is discarded, and the shared connection stays usable
+    let conn = RefCell::new(TcpStream::connect("127.0.0.1:6379").unwrap());
+    let cell = Cell::new(&conn, &b"cell_stream_short"[..], |x: &Vec<u8>| x.clone().into(), |x| x.to_vec());
+    cell.set(b"old".to_vec()).unwrap();
+    assert!(cell.set_from_reader(&mut &b"new"[..], 20).is_err());
+    assert_eq!(cell.get().unwrap(), b"old");
+
+    cell.set_from_reader(&mut &b"new"[..], 3).unwrap();
+    assert_eq!(cell.get().unwrap(), b"new");
+    let mut buf = vec![];
+    assert_eq!(cell.get_to_writer(&mut buf).unwrap(), Some(3));
+}
+
+#[test]
+fn cell_stream_failed_writer() {
+    // the rest of the value is read and dropped, and the shared connection stays usable
+    let conn = RefCell::new(TcpStream::connect("127.0.0.1:6379").unwrap());
+    let cell = Cell::new(&conn, &b"cell_stream_failed"[..], |x: &Vec<u8>| x.clone().into(), |x| x.to_vec());
+    let blob: Vec<u8> = (0..1_000_000).map(|i| (i % 251) as u8).collect();
+    cell.set(&blob).unwrap();
+
+    let e = cell.get_to_writer(&mut DiskFull(100_000)).unwrap_err();
+    assert_eq!(e.to_string(), "io error: disk full");
+    assert_eq!((&conn).arg(b"ping").fetch().unwrap().text(), "PONG");
+    assert_eq!(cell.get().unwrap().len(), blob.len());
+}
+
+// accepts a number of bytes, then fails
+struct DiskFull(usize);
+
+impl Write for DiskFull {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.0.min(buf.len()) {
+            0 => Err(std::io::Error::other("disk full")),
+            n => {
+                self.0 -= n;
+                Ok(n)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
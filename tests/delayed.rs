@@ -0,0 +1,27 @@
+use redis_alchemy::*;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn delayed_queue() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let queue = DelayedQueue::new(&client, &b"delayed"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    let target = List::new(&client, &b"delayed_target"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    queue.clear().unwrap();
+    target.clear().unwrap();
+
+    queue.schedule(1, SystemTime::now() - Duration::from_secs(1)).unwrap();
+    queue.schedule_after(2, Duration::from_millis(200)).unwrap();
+    queue.schedule_after(3, Duration::from_secs(60)).unwrap();
+    queue.schedule_after(4, Duration::from_secs(60)).unwrap();
+    assert!(queue.cancel(4).unwrap());
+    assert!(!queue.cancel(4).unwrap());
+    assert_eq!(queue.len().unwrap(), 3);
+    assert_eq!(queue.peek().unwrap().unwrap().0, 1);
+
+    assert_eq!(queue.poll_due(&target).unwrap(), 1);
+    assert_eq!(queue.poll_due(&target).unwrap(), 0);
+    assert_eq!(queue.wait_due(&target, Duration::from_secs(2)).unwrap(), 1);
+    assert_eq!(queue.wait_due(&target, Duration::from_millis(100)).unwrap(), 0);
+    assert_eq!(target.to_vec().unwrap(), vec![1, 2]);
+    assert_eq!(queue.len().unwrap(), 1);
+}
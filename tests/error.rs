@@ -0,0 +1,46 @@
+use redis_alchemy::*;
+use redis_alchemy::testing::MockConnection;
+use std::cell::RefCell;
+
+#[test]
+fn error_kind() {
+    let mut mock = MockConnection::new();
+    mock.expect(&[b"get", b"a"]).error("MOVED 3999 127.0.0.1:6381")
+        .expect(&[b"get", b"b"]).error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        .expect(&[b"get", b"c"]).error("LOADING Redis is loading the dataset in memory")
+        .expect(&[b"get", b"d"]).error("CUSTOMERR from a module");
+    let conn = RefCell::new(mock);
+
+    let e = conn.arg(b"get").arg(b"a").fetch().unwrap_err();
+    assert_eq!(e.kind(), Some(&ServerErrorKind::Moved(Redirect { slot: 3999, addr: "127.0.0.1:6381".into() })));
+    assert!(e.is_retriable() && !e.is_connection_error());
+
+    let e = conn.arg(b"get").arg(b"b").fetch().unwrap_err();
+    assert_eq!(e.kind(), Some(&ServerErrorKind::WrongType));
+    assert!(!e.is_retriable());
+    assert_eq!(e.to_string(), "redis error: WRONGTYPE Operation against a key holding the wrong kind of value");
+    match e {
+        RedisError::RedisError(e) => assert_eq!(e.detail(), "Operation against a key holding the wrong kind of value"),
+        _ => unreachable!()
+    }
+
+    assert!(conn.arg(b"get").arg(b"c").fetch().unwrap_err().is_retriable());
+    assert_eq!(conn.arg(b"get").arg(b"d").fetch().unwrap_err().kind(), Some(&ServerErrorKind::Other("CUSTOMERR".into())));
+
+    let e = RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+    assert!(e.is_retriable() && e.is_connection_error());
+}
+
+#[test]
+fn error_trait() {
+    fn run(conn: &RefCell<MockConnection>) -> Result<(), Box<dyn std::error::Error>> {
+        let _ = conn.arg(b"ping").fetch()?;
+        Ok(())
+    }
+
+    let conn = RefCell::new(MockConnection::new());
+    conn.borrow_mut().expect(&[b"ping"]).error("NOAUTH Authentication required.");
+    let e = run(&conn).unwrap_err();
+    assert_eq!(e.to_string(), "redis error: NOAUTH Authentication required.");
+    assert!(e.source().is_some());
+}
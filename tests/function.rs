@@ -0,0 +1,30 @@
+use redis_alchemy::*;
+
+#[test]
+fn function() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let cell = Cell::new(&client, &b"function_cell"[..], |x: &i64| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    cell.set(1).unwrap();
+
+    let source = "#!lua name=alchemy_test
+        redis.register_function('alchemy_incr', function(keys, args) return redis.call('incrby', keys[1], args[1]) end)
+        redis.register_function{function_name='alchemy_get', callback=function(keys) return redis.call('get', keys[1]) end, flags={'no-writes'}}";
+    assert_eq!(client.function_load(source, true).unwrap(), "alchemy_test");
+    assert!(client.function_load(source, false).is_err());
+
+    let lib = client.function_list().unwrap().into_iter().find(|x| x.name == "alchemy_test").unwrap();
+    assert_eq!(lib.engine, "LUA");
+    assert_eq!(lib.functions.len(), 2);
+
+    assert_eq!(client.fcall::<i64>("alchemy_incr", &[&cell], &[b"2"]).unwrap(), 3);
+    assert_eq!(client.fcall_ro::<String>("alchemy_get", &[&cell], &[]).unwrap(), "3");
+    assert_eq!(client.fcall_with::<i64, i64>("alchemy_incr", &[&cell], |x| x.to_string().into_bytes().into(), &[4]).unwrap(), 7);
+    assert_eq!(client.fcall_ro_with::<i64, i64>("alchemy_get", &[&cell], |x| x.to_string().into_bytes().into(), &[0]).unwrap(), 7);
+
+    let dump = client.function_dump().unwrap();
+    client.function_delete("alchemy_test").unwrap();
+    assert!(client.fcall::<i64>("alchemy_incr", &[&cell], &[b"2"]).is_err());
+    client.function_restore(&dump, RestorePolicy::Replace).unwrap();
+    assert_eq!(client.fcall::<i64>("alchemy_incr", &[&cell], &[b"2"]).unwrap(), 9);
+    client.function_delete("alchemy_test").unwrap();
+}
@@ -0,0 +1,39 @@
+use redis_alchemy::*;
+
+#[test]
+fn geo() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let geo = Geo::new(&client, &b"geo"[..], |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap());
+    geo.clear().unwrap();
+
+    assert!(geo.insert("Palermo".to_string(), Coordinates::new(13.361389, 38.115556)).unwrap());
+    assert_eq!(geo.extend(&[
+        ("Catania".to_string(), Coordinates::new(15.087269, 37.502669)),
+        ("Rome".to_string(), Coordinates::new(12.496366, 41.902782))
+    ]).unwrap(), 2);
+    assert_eq!(geo.len().unwrap(), 3);
+
+    let pos = geo.position("Palermo".to_string()).unwrap().unwrap();
+    assert!((pos.longitude - 13.361389).abs() < 1e-4);
+    assert!(geo.position("Nowhere".to_string()).unwrap().is_none());
+
+    let dist = geo.distance("Palermo".to_string(), "Catania".to_string(), Unit::Kilometers).unwrap().unwrap();
+    assert!((dist - 166.27).abs() < 0.1);
+    assert!(geo.distance("Palermo".to_string(), "Nowhere".to_string(), Unit::Meters).unwrap().is_none());
+
+    let options = GeoSearchOptions { with_dist: true, with_coord: true, order: Some(SortOrder::Asc), ..Default::default() };
+    let res = geo.search(GeoCenter::Point(Coordinates::new(15.0, 37.0)), GeoShape::Radius(200.0, Unit::Kilometers), &options).unwrap();
+    assert_eq!(res.iter().map(|x| &x.member[..]).collect::<Vec<_>>(), vec!["Catania", "Palermo"]);
+    assert!(res[0].distance.unwrap() < res[1].distance.unwrap());
+    assert!(res[1].coordinates.is_some());
+
+    let options = GeoSearchOptions { count: Some(1), order: Some(SortOrder::Desc), ..Default::default() };
+    let res = geo.search(GeoCenter::Member(&"Rome".to_string()), GeoShape::Box(2000.0, 2000.0, Unit::Kilometers), &options).unwrap();
+    assert_eq!(res[0].member, "Catania");
+    assert!(res[0].distance.is_none());
+
+    let dest = Geo::new(&client, &b"geo_dest"[..], |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap());
+    let stored = geo.search_store(GeoCenter::Member(&"Rome".to_string()), GeoShape::Radius(500.0, Unit::Kilometers), &Default::default(), &dest, false).unwrap();
+    assert_eq!(stored, 2);
+    assert!(dest.position("Palermo".to_string()).unwrap().is_some());
+}
@@ -0,0 +1,21 @@
+use redis_alchemy::*;
+
+#[test]
+fn hyperloglog() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let a = HyperLogLog::new(&client, &b"hyperloglog_a"[..], |x: &i32| x.to_string().into_bytes().into());
+    let b = HyperLogLog::new(&client, &b"hyperloglog_b"[..], |x: &i32| x.to_string().into_bytes().into());
+    a.clear().unwrap();
+    b.clear().unwrap();
+
+    assert!(a.add(1).unwrap());
+    assert!(!a.add(1).unwrap());
+    assert!(a.add_many(&[2, 3, 4]).unwrap());
+    assert_eq!(a.count().unwrap(), 4);
+
+    b.add_many(&[3, 4, 5]).unwrap();
+    assert_eq!(a.count_union(&[&b]).unwrap(), 5);
+    a.merge_into(&b).unwrap();
+    assert_eq!(b.count().unwrap(), 5);
+    assert_eq!(a.count().unwrap(), 4);
+}
@@ -0,0 +1,36 @@
+use redis_alchemy::*;
+use std::time::Duration;
+
+#[test]
+fn lock() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let lock = Lock::new(&client, &b"lock"[..]);
+    let other = Lock::new(&client, &b"lock"[..]);
+
+    assert!(lock.try_acquire(Duration::from_micros(500)).is_err());
+    let guard = lock.try_acquire(Duration::from_secs(10)).unwrap().unwrap();
+    assert!(other.try_acquire(Duration::from_secs(10)).unwrap().is_none());
+    assert!(other.acquire(Duration::from_secs(10), Duration::from_millis(120)).unwrap().is_none());
+    assert!(guard.extend(Duration::from_secs(20)).unwrap());
+    drop(guard);
+
+    let guard = other.acquire(Duration::from_millis(100), Duration::from_secs(1)).unwrap().unwrap();
+    std::thread::sleep(Duration::from_millis(200)); // expired
+    let guard2 = lock.try_acquire(Duration::from_secs(10)).unwrap().unwrap();
+    assert!(!guard.extend(Duration::from_secs(10)).unwrap());
+    assert!(!guard.release().unwrap()); // must not release the lock held by guard2
+    assert!(guard2.release().unwrap());
+}
+
+#[test]
+fn redlock() {
+    let client = TcpClient::new("127.0.0.1:6379"); // a single instance since all of them would share the same key here
+    let lock = Redlock::new(vec![&client], &b"redlock"[..]);
+
+    let mut guard = lock.try_acquire(Duration::from_secs(10)).unwrap();
+    assert!(guard.valid_until() > std::time::Instant::now());
+    assert!(guard.extend(Duration::from_secs(10)));
+    assert!(lock.acquire(Duration::from_secs(10), Duration::from_millis(120)).is_none());
+    drop(guard);
+    assert!(lock.try_acquire(Duration::from_secs(10)).is_some());
+}
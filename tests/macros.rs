@@ -0,0 +1,56 @@
+use redis_alchemy::*;
+use redis_alchemy::testing::*;
+use std::cell::RefCell;
+use std::io::prelude::*;
+
+// counts the writes to the connection
+struct Counting(FakeConnection, usize);
+
+impl Read for Counting {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for Counting {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.1 += 1;
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[test]
+fn macro_cmd() {
+    let server = FakeRedis::new();
+    assert_eq!(cmd!(&server, "hset", b"macro_h", "f", 40).fetch().unwrap().integer(), 1);
+    assert_eq!(cmd!(&server, "hincrby", "macro_h", "f", 2,).fetch().unwrap().integer(), 42);
+    assert_eq!(cmd!(&server, "rpush", b"macro_l", ["a", "b"], None::<&str>).fetch().unwrap().integer(), 2);
+    assert_eq!(cmd!(&server, "hget", b"macro_h", "f").fetch().unwrap().as_bytes(), b"42");
+
+    let mut mock = MockConnection::new();
+    mock.expect(&[b"zadd", b"z", b"1.5", b"alice"]).reply(Response::Integer(1));
+    let conn = RefCell::new(mock);
+    assert_eq!(cmd!(&conn, "zadd", b"z", 1.5, "alice").fetch().unwrap().integer(), 1);
+}
+
+#[test]
+fn macro_cmd_pipe() {
+    let server = FakeRedis::new();
+    let conn = RefCell::new(Counting(server.connect(), 0));
+    let replies = cmd_pipe!(&conn, ["set", b"pipe_k", 1], ["rpush", b"pipe_l", 2, 3], ["get", b"pipe_k"]).unwrap();
+    assert_eq!(conn.borrow().1, 1);
+    assert_eq!(replies.len(), 3);
+    assert_eq!(replies[1].as_ref().unwrap().as_integer(), 2);
+    assert_eq!(replies[2].as_ref().unwrap().as_bytes(), b"1");
+
+    // an error in the middle does not hide the other replies, and the connection stays usable
+    let replies = cmd_pipe!(&conn, ["lpop", b"pipe_l"], ["rpush", b"pipe_k", "x"], ["lpop", b"pipe_l"]).unwrap();
+    assert_eq!(replies[0].as_ref().unwrap().as_bytes(), b"2");
+    assert!(replies[1].is_err());
+    assert_eq!(replies[2].as_ref().unwrap().as_bytes(), b"3");
+    assert_eq!(cmd!(&conn, "llen", b"pipe_l").fetch().unwrap().integer(), 0);
+}
@@ -9,16 +9,16 @@ fn map() {
     );
     map.clear().unwrap();
 
-    map.insert(&"a".to_string(), &2).unwrap();
-    map.insert(&"b".to_string(), &3).unwrap();
+    map.insert("a".to_string(), 2).unwrap();
+    map.insert("b".to_string(), 3).unwrap();
 
-    assert_eq!(map.get(&"a".to_string()).unwrap(), Some(2));
+    assert_eq!(map.get("a".to_string()).unwrap(), Some(2));
     assert_eq!(map.len().unwrap(), 2);
-    assert!(map.contains_key(&"b".to_string()).unwrap());
-    map.remove(&"b".to_string()).unwrap();
-    assert!(!map.contains_key(&"b".to_string()).unwrap());
+    assert!(map.contains_key("b".to_string()).unwrap());
+    map.remove("b".to_string()).unwrap();
+    assert!(!map.contains_key("b".to_string()).unwrap());
     assert!(!map.is_empty().unwrap());
-    map.remove(&"a".to_string()).unwrap();
+    map.remove("a".to_string()).unwrap();
     assert!(map.is_empty().unwrap());
 }
 
@@ -32,7 +32,7 @@ fn map_iter() {
     map.clear().unwrap();
 
     for i in 0..1000 {
-        map.insert(&i.to_string(), &i).unwrap();
+        map.insert(i.to_string(), i).unwrap();
     }
 
     let mut pairs: Vec<_> = map.iter().collect();
@@ -40,3 +40,132 @@ fn map_iter() {
     assert_eq!(pairs[233].1, 233);
     assert_eq!(pairs[666].0, "666".to_string());
 }
+
+#[test]
+fn map_bulk() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let map = Map::new(&client, &b"map_bulk"[..],
+        |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap(),
+        |x: &i32| format!("{}", x).into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    );
+    map.clear().unwrap();
+
+    let pairs: Vec<_> = (0..1000).map(|i| (i.to_string(), i)).collect();
+    map.extend(&pairs).unwrap();
+    assert_eq!(map.len().unwrap(), 1000);
+    assert_eq!(map.to_hashmap().unwrap()["233"], 233);
+    assert_eq!(map.keys().unwrap().count(), 1000);
+    assert_eq!(map.values().unwrap().sum::<i32>(), 499500);
+
+    let fields = ["1".to_string(), "x".to_string(), "2".to_string()];
+    assert_eq!(map.get_many(&fields).unwrap(), vec![Some(1), None, Some(2)]);
+
+    assert!(!map.insert_if_absent("1".to_string(), 5).unwrap());
+    assert!(map.insert_if_absent("x".to_string(), 5).unwrap());
+    assert_eq!(map.increment("x".to_string(), 3).unwrap(), 8);
+    assert_eq!(map.increment_float("y".to_string(), 1.5).unwrap(), 1.5);
+
+    assert_eq!(map.remove_many(&fields).unwrap(), 3);
+    assert_eq!(map.len().unwrap(), 999);
+}
+
+// the major and minor version of the server
+fn server_version(client: &TcpClient<&str>) -> (u32, u32) {
+    let info = client.arg(b"info").arg(b"server").fetch().unwrap().bytes();
+    let info = String::from_utf8(info.to_vec()).unwrap();
+    let version = info.lines().find_map(|x| x.strip_prefix("redis_version:")).unwrap();
+    let mut parts = version.split('.').map(|x| x.parse().unwrap());
+    (parts.next().unwrap(), parts.next().unwrap())
+}
+
+#[test]
+fn map_field_ttl() {
+    use std::time::Duration;
+
+    let client = TcpClient::new("127.0.0.1:6379");
+    let version = server_version(&client);
+    if version < (7, 4) {
+        eprintln!("skipped: field expiration requires Redis 7.4");
+        return
+    }
+    let map = Map::new(&client, &b"map_field_ttl"[..],
+        |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap(),
+        |x: &i32| format!("{}", x).into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    );
+    map.clear().unwrap();
+
+    let a = "a".to_string();
+    assert_eq!(map.expire_field(&a, Duration::from_secs(10)).unwrap(), FieldStatus::NoSuchField);
+    map.insert(&a, 1).unwrap();
+    assert_eq!(map.field_ttl(&a).unwrap(), FieldTtl::Persistent);
+    assert_eq!(map.expire_field(&a, Duration::from_secs(10)).unwrap(), FieldStatus::Updated);
+    assert!(matches!(map.field_ttl(&a).unwrap(), FieldTtl::Expires(_)));
+    assert_eq!(map.persist_field(&a).unwrap(), FieldStatus::Updated);
+    assert_eq!(map.persist_field(&a).unwrap(), FieldStatus::Unchanged);
+
+    if version < (8, 0) {
+        eprintln!("skipped: hsetex and hgetex require Redis 8.0");
+        return
+    }
+    map.insert_with_ttl("b".to_string(), 2, Duration::from_millis(100)).unwrap();
+    assert_eq!(map.get_and_expire("b".to_string(), Duration::from_secs(10)).unwrap(), Some(2));
+    map.insert_with_ttl("c".to_string(), 3, Duration::from_millis(100)).unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(map.get("c".to_string()).unwrap(), None);
+    assert_eq!(map.get("b".to_string()).unwrap(), Some(2));
+}
+
+#[test]
+fn map_entry() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let map = Map::new(&client, &b"map_entry"[..],
+        |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap(),
+        |x: &i32| format!("{}", x).into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    );
+    map.clear().unwrap();
+
+    assert_eq!(map.entry("a".to_string()).or_insert(1).unwrap(), 1);
+    assert_eq!(map.entry("a".to_string()).or_insert(2).unwrap(), 1);
+    assert_eq!(map.entry("b".to_string()).or_default().unwrap(), 0);
+    assert_eq!(map.entry("a".to_string()).and_modify(|x| *x += 10).or_insert_with(|| unreachable!()).unwrap(), 11);
+    assert_eq!(map.entry("c".to_string()).and_modify(|x| *x += 10).or_insert(5).unwrap(), 5);
+    assert_eq!(map.get("c".to_string()).unwrap(), Some(5));
+
+    let handles: Vec<_> = (0..4).map(|_| oh_my_rust::scoped_spawn(|| {
+        for _ in 0..25 {
+            map.entry("counter".to_string()).and_modify(|x| *x += 1).or_insert(1).unwrap();
+        }
+    })).collect();
+    for handle in handles {
+        handle.join().unwrap()
+    }
+    assert_eq!(map.get("counter".to_string()).unwrap(), Some(100));
+}
+
+#[test]
+fn map_entry_cleanup() {
+    use redis_alchemy::testing::MockConnection;
+    use std::cell::RefCell;
+
+    let mut mock = MockConnection::new();
+    mock.expect(&[b"watch", b"map"]).reply(Response::Text("OK".into()))
+        .expect(&[b"hget", b"map", b"a"]).reply(Response::Bytes(b"1"[..].into()))
+        .expect(&[b"multi"]).reply(Response::Text("OK".into()))
+        .expect(&[b"hset", b"map", b"a", b"2"]).error("OOM command not allowed when used memory > 'maxmemory'")
+        .expect(&[b"discard"]).reply(Response::Text("OK".into()))
+        .expect(&[b"watch", b"map"]).reply(Response::Text("OK".into()))
+        .expect(&[b"hget", b"map", b"a"]).reply(Response::Bytes(b"1"[..].into()))
+        .expect(&[b"unwatch"]).reply(Response::Text("OK".into()));
+    let conn = RefCell::new(mock);
+    let map = Map::new(&conn, &b"map"[..],
+        |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap(),
+        |x: &i32| format!("{}", x).into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    );
+
+    assert!(map.entry("a".to_string()).and_modify(|x| *x += 1).or_insert(0).is_err());
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        map.entry("a".to_string()).and_modify(|_| panic!("modifier failed")).or_insert(0)
+    }));
+    assert!(panicked.is_err());
+    conn.borrow().verify();
+}
@@ -0,0 +1,125 @@
+use redis_alchemy::*;
+use std::cell::RefCell;
+use std::io::prelude::*;
+use std::net::TcpStream;
+
+#[test]
+fn parser_incremental() {
+    let data = b"*3\r\n$5\r\nhello\r\n:42\r\n*2\r\n$-1\r\n+OK\r\n$3\r\nfoo\r\n";
+    let mut parser = RespParser::default();
+    let mut replies = vec![];
+    for &b in data.iter() {
+        parser.feed(&[b]);
+        while let Some(x) = parser.parse().unwrap() {
+            replies.push(x)
+        }
+    }
+    assert_eq!(parser.buffered(), 0);
+    assert_eq!(replies.len(), 2);
+    assert_eq!(format!("{:#}", replies[0]), r#"["hello", (integer) 42, [(nil), OK]]"#);
+    assert_eq!(replies[1].as_bytes(), b"foo");
+}
+
+#[test]
+fn parser_errors() {
+    let mut parser = RespParser::default();
+    parser.feed(b"*2\r\n-ERR first\r\n:1\r\n-ERR second\r\n:2\r\n");
+    assert!(matches!(parser.parse(), Err(RedisError::RedisError(x)) if x.message == "ERR first"));
+    assert!(matches!(parser.parse(), Err(RedisError::RedisError(x)) if x.message == "ERR second"));
+    assert_eq!(parser.parse().unwrap().unwrap().integer(), 2);
+
+    let mut parser = RespParser::new(RespLimits { max_bulk: 4, max_array: 2 });
+    parser.feed(b"$5\r\n");
+    assert!(parser.parse().is_err());
+    let mut parser = RespParser::new(RespLimits { max_bulk: 4, max_array: 2 });
+    parser.feed(b"*3\r\n");
+    assert!(parser.parse().is_err());
+    let mut parser = RespParser::new(RespLimits { max_bulk: 4, max_array: 2 });
+    parser.feed(b"+OK OK");
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn parser_pipeline() {
+    let server = testing::FakeRedis::new();
+    let mut conn = server.connect();
+    let mut sess = Session::new(&mut conn);
+    for i in 0..100 {
+        sess.arg(b"rpush").arg(b"list").arg(i.to_string().as_bytes()).send().unwrap();
+    }
+    sess.arg(b"lrange").arg(b"list").arg(b"0").arg(b"-1").send().unwrap();
+    for i in 0..100 {
+        assert_eq!(sess.recv().unwrap().integer(), i + 1);
+    }
+    assert_eq!(sess.recv().unwrap().list().len(), 100);
+}
+
+#[test]
+fn parser_bulk_in_pieces() {
+    // a large bulk string read a little at a time must not cost quadratic time
+    let mut data = b"$8000000\r\n".to_vec();
+    data.extend((0..8_000_000).map(|i| (i % 251) as u8));
+    data.extend(b"\r\n");
+    let mut parser = RespParser::default();
+    let mut pieces = Pieces(&data);
+    assert_eq!(parser.read_reply(&mut pieces).unwrap().as_bytes().len(), 8_000_000);
+    assert_eq!(parser.buffered(), 0);
+}
+
+// yields at most 1000 bytes per read, like a slow connection
+struct Pieces<'a>(&'a [u8]);
+
+impl Read for Pieces<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(1000);
+        self.0.read(&mut buf[..n])
+    }
+}
+
+// replies with fixed bytes regardless of the commands
+struct Canned(&'static [u8]);
+
+impl Read for Canned {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for Canned {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn parser_session_sync() {
+    // the responses a Session did not receive are read when it is dropped, so the next Session gets its own
+    let server = testing::FakeRedis::new();
+    let conn = RefCell::new(server.connect());
+    let mut sess = Session::new(conn.borrow_mut());
+    sess.arg(b"set").arg(b"k").arg(b"1").send().unwrap();
+    sess.arg(b"get").arg(b"k").send().unwrap();
+    assert_eq!(sess.recv().unwrap().text(), "OK");
+    drop(sess);
+    assert!((&conn).arg(b"get").arg(b"x").fetch().unwrap().is_nothing());
+
+    // bytes after the last response would be lost with the Session
+    let mut conn = Canned(b"+OK\r\n+OK\r\n");
+    assert!(matches!(Session::new(&mut conn).arg(b"ping").fetch(), Err(RedisError::ProtocolError(_))));
+}
+
+#[test]
+fn parser_session_panic() {
+    // unwinding does not wait for a response that never comes
+    let mut conn = TcpStream::connect("127.0.0.1:6379").unwrap();
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut sess = Session::new(&mut conn);
+        sess.arg(b"blpop").arg(b"parser_session_panic").arg(0).send().unwrap();
+        panic!("no response")
+    }));
+    assert!(res.is_err());
+}
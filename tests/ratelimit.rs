@@ -0,0 +1,54 @@
+use redis_alchemy::*;
+use std::time::Duration;
+
+fn exhaust(limiter: &RateLimiter<TcpClient<&str>, &TcpClient<&str>, &[u8]>, n: u64) {
+    limiter.reset(&b"user"[..]).unwrap();
+    for i in 0..n {
+        match limiter.check(&b"user"[..]).unwrap() {
+            RateLimit::Allowed { remaining, .. } => assert_eq!(remaining, n - i - 1),
+            x => panic!("unexpected {:?}", x)
+        }
+    }
+    match limiter.check(&b"user"[..]).unwrap() {
+        RateLimit::Denied { retry_after } => assert!(retry_after > Duration::from_millis(0)),
+        x => panic!("unexpected {:?}", x)
+    }
+}
+
+#[test]
+fn rate_limit() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let window = Duration::from_millis(300);
+
+    let limiter = RateLimiter::new(&client, &b"ratelimit_fixed:"[..], RateLimitStrategy::FixedWindow { limit: 3, window }).unwrap();
+    exhaust(&limiter, 3);
+    std::thread::sleep(window);
+    assert!(limiter.check(&b"user"[..]).unwrap().is_allowed());
+
+    let limiter = RateLimiter::new(&client, &b"ratelimit_sliding:"[..], RateLimitStrategy::SlidingLog { limit: 3, window }).unwrap();
+    limiter.reset(&b"user"[..]).unwrap();
+    limiter.check(&b"user"[..]).unwrap();
+    match limiter.check(&b"user"[..]).unwrap() {
+        RateLimit::Allowed { reset_after, .. } => assert_eq!(reset_after, window), // restored when the newest request expires
+        x => panic!("unexpected {:?}", x)
+    }
+    exhaust(&limiter, 3);
+    std::thread::sleep(window);
+    assert!(limiter.check(&b"user"[..]).unwrap().is_allowed());
+
+    let limiter = RateLimiter::new(&client, &b"ratelimit_bucket:"[..], RateLimitStrategy::TokenBucket { capacity: 3, interval: Duration::from_millis(100) }).unwrap();
+    exhaust(&limiter, 3);
+    std::thread::sleep(Duration::from_millis(150));
+    assert!(limiter.check(&b"user"[..]).unwrap().is_allowed());
+    assert!(!limiter.check(&b"user"[..]).unwrap().is_allowed());
+}
+
+#[test]
+fn rate_limit_invalid() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let window = Duration::from_millis(300);
+    assert!(RateLimiter::new(&client, &b"ratelimit_invalid:"[..], RateLimitStrategy::SlidingLog { limit: 0, window }).is_err());
+    assert!(RateLimiter::new(&client, &b"ratelimit_invalid:"[..], RateLimitStrategy::FixedWindow { limit: 0, window }).is_err());
+    assert!(RateLimiter::new(&client, &b"ratelimit_invalid:"[..], RateLimitStrategy::TokenBucket { capacity: 3, interval: Duration::from_micros(500) }).is_err());
+    assert!(RateLimiter::new(&client, &b"ratelimit_invalid:"[..], RateLimitStrategy::SlidingLog { limit: 3, window: Duration::ZERO }).is_err());
+}
@@ -0,0 +1,51 @@
+use redis_alchemy::*;
+
+fn sample() -> Response {
+    Response::List(vec![
+        Response::Bytes(b"a \"b\"\n\x01"[..].into()),
+        Response::Integer(-1),
+        Response::Nothing,
+        Response::List(vec![Response::Text("OK".into()), Response::List(vec![])]),
+        Response::List((0..10).map(Response::Integer).collect())
+    ])
+}
+
+#[test]
+fn response_write() {
+    let mut buf = vec![];
+    sample().write_resp(&mut buf).unwrap();
+    assert_eq!(&buf[..27], &b"*5\r\n$7\r\na \"b\"\n\x01\r\n:-1\r\n$-1\r\n"[..]);
+    assert!(buf.ends_with(b"*2\r\n+OK\r\n*0\r\n*10\r\n:0\r\n:1\r\n:2\r\n:3\r\n:4\r\n:5\r\n:6\r\n:7\r\n:8\r\n:9\r\n"));
+
+    let mut buf = vec![];
+    Response::List(vec![Response::Nothing]).write_resp3(&mut buf).unwrap();
+    assert_eq!(&buf[..], b"*1\r\n_\r\n");
+
+    // round trip through the parser
+    let client = std::cell::RefCell::new(redis_alchemy::testing::MockConnection::new());
+    client.borrow_mut().expect(&[b"x"]).reply(sample());
+    let x = client.arg(b"x").fetch().unwrap();
+    assert_eq!(x.to_string(), sample().to_string());
+}
+
+#[test]
+fn response_display() {
+    assert_eq!(sample().to_string(), r#"1) "a \"b\"\n\x01"
+2) (integer) -1
+3) (nil)
+4) 1) OK
+   2) (empty array)
+5)  1) (integer) 0
+    2) (integer) 1
+    3) (integer) 2
+    4) (integer) 3
+    5) (integer) 4
+    6) (integer) 5
+    7) (integer) 6
+    8) (integer) 7
+    9) (integer) 8
+   10) (integer) 9"#);
+    assert_eq!(format!("{:#}", sample()), r#"["a \"b\"\n\x01", (integer) -1, (nil), [OK, (empty array)], [(integer) 0, (integer) 1, (integer) 2, (integer) 3, (integer) 4, (integer) 5, (integer) 6, (integer) 7, (integer) 8, (integer) 9]]"#);
+    assert_eq!(Response::Integer(3).to_string(), "(integer) 3");
+    assert_eq!(Response::List(vec![]).to_string(), "(empty array)");
+}
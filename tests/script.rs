@@ -0,0 +1,32 @@
+use redis_alchemy::*;
+
+#[test]
+fn script() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let list = List::new(&client, &b"script_list"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    list.clear().unwrap();
+    list.extend(&[1, 2, 3]).unwrap();
+
+    let script = Script::new("redis.call('rpush', KEYS[1], ARGV[1]); return redis.call('lrange', KEYS[1], 0, -1)");
+    assert_eq!(script.hash().len(), 40);
+
+    Script::flush(&client).unwrap();
+    assert!(!script.exists(&client).unwrap());
+    let res: Vec<String> = script.invoke(&client, &[&list], &[b"4"]).unwrap();
+    assert_eq!(res, vec!["1", "2", "3", "4"]);
+    let res: Vec<i64> = script.invoke_with(&client, &[&list], |x: &i32| x.to_string().into_bytes().into(), &[5]).unwrap();
+    assert_eq!(res, vec![1, 2, 3, 4, 5]);
+    assert!(script.exists(&client).unwrap());
+
+    Script::flush(&client).unwrap();
+    script.load(&client).unwrap();
+    assert!(script.exists(&client).unwrap());
+
+    let script = Script::new("return redis.call('get', KEYS[1])");
+    assert!(script.invoke::<Option<i64>>(&client, &[&list], &[]).is_err()); // WRONGTYPE
+    let cell = Cell::new(&client, &b"script_cell"[..], |x: &i64| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    cell.clear().unwrap();
+    assert_eq!(script.invoke::<Option<i64>>(&client, &[&cell], &[]).unwrap(), None);
+    cell.set(42).unwrap();
+    assert_eq!(script.invoke::<Option<i64>>(&client, &[&cell], &[]).unwrap(), Some(42));
+}
@@ -0,0 +1,90 @@
+use redis_alchemy::*;
+use redis_alchemy::server::*;
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::sync::Mutex;
+
+fn kv() -> impl Handler {
+    let data = Mutex::new(HashMap::new());
+    move |args: &[bytes::Bytes]| match (&args[0].to_ascii_lowercase()[..], &args[1..]) {
+        (b"ping", []) => Ok(Response::Text("PONG".into())),
+        (b"get", [key]) => Ok(data.lock().unwrap().get(key).cloned().map(Response::Bytes).unwrap_or(Response::Nothing)),
+        (b"set", [key, value]) => {
+            data.lock().unwrap().insert(key.clone(), value.clone());
+            Ok(Response::Text("OK".into()))
+        },
+        (b"del", keys) => Ok(Response::Integer(keys.iter().filter(|x| data.lock().unwrap().remove(*x).is_some()).count() as _)),
+        _ => Err(format!("ERR unknown command '{}'", String::from_utf8_lossy(&args[0])))
+    }
+}
+
+#[test]
+fn server_tcp() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Server::new(kv());
+    std::thread::spawn(move || server.serve_tcp(listener));
+
+    let client = TcpClient::new(addr);
+    let cell = Cell::new(&client, &b"server_cell"[..], |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap());
+    cell.set("yes".to_string()).unwrap();
+    assert_eq!(&cell.get().unwrap()[..], "yes");
+    cell.clear().unwrap();
+    assert!(client.arg(b"lpush").arg(b"server_cell").fetch().is_err());
+
+    // inline commands and pipelining
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    stream.write_all(b"PING\r\nset a 1\r\n*2\r\n$3\r\nget\r\n$1\r\na\r\n").unwrap();
+    let mut buf = [0; 19];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"+PONG\r\n+OK\r\n$1\r\n1\r\n");
+}
+
+#[test]
+fn server_unix() {
+    let path = std::env::temp_dir().join(format!("redis_alchemy_server_{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let server = Server::new(kv());
+    std::thread::spawn(move || server.serve_unix(listener));
+
+    let client = UnixClient::new(&path);
+    assert_eq!(client.arg(b"ping").fetch().unwrap().text(), "PONG");
+    assert!(client.arg(b"get").arg(b"x").fetch().unwrap().is_nothing());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn server_codec() {
+    let mut r = &b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$0\r\n\r\nget  k\n"[..];
+    let mut parser = RespParser::default();
+    let args = read_command(&mut parser, &mut r).unwrap().unwrap();
+    assert_eq!(args, vec![&b"set"[..], b"k", b""]);
+    assert_eq!(read_command(&mut parser, &mut r).unwrap().unwrap(), vec![&b"get"[..], b"k"]);
+    assert!(read_command(&mut parser, &mut r).unwrap().is_none());
+
+    let mut buf = vec![];
+    write_reply(&mut buf, &Ok(Response::List(vec![Response::Integer(1), Response::Nothing, Response::Bytes(b"x"[..].into())]))).unwrap();
+    write_reply(&mut buf, &Err("ERR bad\r\nthing".into())).unwrap();
+    assert_eq!(&buf[..], &b"*3\r\n:1\r\n$-1\r\n$1\r\nx\r\n-ERR bad  thing\r\n"[..]);
+}
+
+#[test]
+fn server_limits() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Server::new(kv()).limits(RespLimits { max_bulk: 16, max_array: 4 });
+    std::thread::spawn(move || server.serve_tcp(listener));
+
+    let client = TcpClient::new(addr);
+    assert_eq!(client.arg(b"set").arg(b"k").arg([b'x'; 16]).fetch().unwrap().text(), "OK");
+
+    // the oversized header is rejected before any payload is read
+    for command in [&b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1000000000\r\n"[..], b"*1000000000\r\n"] {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(command).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert!(reply.starts_with("-ERR Protocol error"), "{}", reply);
+    }
+}
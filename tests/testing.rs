@@ -0,0 +1,203 @@
+use redis_alchemy::*;
+use redis_alchemy::testing::*;
+use std::cell::RefCell;
+use std::time::Duration;
+
+#[test]
+fn fake_cell() {
+    let server = FakeRedis::new();
+    let cell = Cell::new(&server, &b"cell"[..], |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap());
+    cell.set("yes".to_string()).unwrap();
+    assert_eq!(&cell.get().unwrap()[..], "yes");
+    cell.set("no".to_string()).unwrap();
+    assert_eq!(&cell.get().unwrap()[..], "no");
+    cell.clear().unwrap();
+}
+
+#[test]
+#[allow(clippy::reversed_empty_ranges)] // negative indexes count from the end
+fn fake_list() {
+    let server = FakeRedis::new();
+    let conn = RefCell::new(server.connect());
+    let list = List::new(&conn, &b"list"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+
+    list.extend(&[2, 3]).unwrap();
+    list.push(4).unwrap();
+    list.push_front(1).unwrap();
+    assert_eq!(list.len().unwrap(), 4);
+    assert_eq!(list.get(-1).unwrap(), Some(4));
+    assert!(list.get(5).unwrap().is_none());
+    list.set(2, 5).unwrap();
+    assert!(list.set(9, 5).is_err());
+    assert_eq!(&list.range(..).unwrap()[..], &[1, 2, 5, 4]);
+    assert_eq!(&list.range(1..-1).unwrap()[..], &[2, 5]);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 5, 4]);
+    assert_eq!(list.pop().unwrap(), Some(4));
+    assert_eq!(list.pop_front().unwrap(), Some(1));
+    assert_eq!(list.to_vec().unwrap(), vec![2, 5]);
+}
+
+#[test]
+fn fake_list_blocking() {
+    let server = FakeRedis::new();
+    let list = List::new(&server, &b"list"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    assert_eq!(list.recv(1).unwrap(), None);
+
+    let handle = oh_my_rust::scoped_spawn(|| {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        list.push(39).unwrap();
+    });
+    assert_eq!(list.recv(0).unwrap(), Some(39));
+    handle.join().unwrap();
+}
+
+#[test]
+fn fake_map() {
+    let server = FakeRedis::new();
+    let pool: Pool<_> = (0..4).map(|_| Box::new(server.connect())).collect();
+    let map = Map::new(&pool, &b"map"[..],
+        |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap(),
+        |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    );
+
+    let pairs: Vec<_> = (0..1000).map(|i| (i.to_string(), i)).collect();
+    map.extend(&pairs).unwrap();
+    assert_eq!(map.len().unwrap(), 1000);
+    assert_eq!(map.get("233".to_string()).unwrap(), Some(233));
+    assert_eq!(map.iter().count(), 1000);
+    assert_eq!(map.values().unwrap().sum::<i32>(), 499500);
+    assert_eq!(map.get_many(&["1".to_string(), "x".to_string()]).unwrap(), vec![Some(1), None]);
+    assert!(map.insert_if_absent("x".to_string(), 5).unwrap());
+    assert_eq!(map.increment("x".to_string(), 3).unwrap(), 8);
+    assert_eq!(map.increment_float("y".to_string(), 1.5).unwrap(), 1.5);
+    assert_eq!(map.remove_many(&["x".to_string(), "y".to_string(), "z".to_string()]).unwrap(), 2);
+    assert!(map.contains_key("1".to_string()).unwrap());
+    map.clear().unwrap();
+    assert!(map.is_empty().unwrap());
+}
+
+#[test]
+fn fake_map_entry() {
+    let server = FakeRedis::new();
+    let map = Map::new(&server, &b"map"[..],
+        |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap(),
+        |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    );
+
+    assert_eq!(map.entry("a".to_string()).or_insert(1).unwrap(), 1);
+    assert_eq!(map.entry("a".to_string()).and_modify(|x| *x += 10).or_default().unwrap(), 11);
+
+    let handles: Vec<_> = (0..4).map(|_| oh_my_rust::scoped_spawn(|| {
+        for _ in 0..25 {
+            map.entry("counter".to_string()).and_modify(|x| *x += 1).or_insert(1).unwrap();
+        }
+    })).collect();
+    for handle in handles {
+        handle.join().unwrap()
+    }
+    assert_eq!(map.get("counter".to_string()).unwrap(), Some(100));
+}
+
+#[test]
+fn fake_map_field_ttl() {
+    let server = FakeRedis::new();
+    let map = Map::new(&server, &b"map"[..],
+        |x: &String| x.as_bytes().into(), |x| String::from_utf8(x.to_vec()).unwrap(),
+        |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap()
+    );
+
+    let a = "a".to_string();
+    assert_eq!(map.expire_field(&a, Duration::from_secs(10)).unwrap(), FieldStatus::NoSuchField);
+    map.insert(&a, 1).unwrap();
+    assert_eq!(map.field_ttl(&a).unwrap(), FieldTtl::Persistent);
+    assert_eq!(map.expire_field(&a, Duration::from_secs(10)).unwrap(), FieldStatus::Updated);
+    assert!(matches!(map.field_ttl(&a).unwrap(), FieldTtl::Expires(x) if x > Duration::from_secs(9)));
+    assert_eq!(map.persist_field(&a).unwrap(), FieldStatus::Updated);
+    assert_eq!(map.persist_field(&a).unwrap(), FieldStatus::Unchanged);
+    assert_eq!(map.expire_field(&a, Duration::ZERO).unwrap(), FieldStatus::Deleted);
+    assert!(map.is_empty().unwrap());
+
+    map.insert_with_ttl("b".to_string(), 2, Duration::from_millis(100)).unwrap();
+    assert_eq!(map.get_and_expire("b".to_string(), Duration::from_secs(10)).unwrap(), Some(2));
+    map.insert_with_ttl("c".to_string(), 3, Duration::from_millis(100)).unwrap();
+    map.insert_with_ttl("d".to_string(), 4, Duration::from_millis(100)).unwrap();
+    map.insert("d".to_string(), 5).unwrap(); // overwriting removes the ttl
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(map.get("c".to_string()).unwrap(), None);
+    assert_eq!(map.get("b".to_string()).unwrap(), Some(2));
+    assert_eq!(map.get("d".to_string()).unwrap(), Some(5));
+    assert_eq!(map.len().unwrap(), 2);
+}
+
+#[test]
+fn fake_bitvec() {
+    let server = FakeRedis::new();
+    let a = BitVec::new(&server, &b"a"[..]);
+    let b = BitVec::new(&server, &b"b"[..]);
+    let c = BitVec::new(&server, &b"c"[..]);
+
+    assert!(a.find_first().unwrap().is_none());
+    a.set(2, true).unwrap();
+    assert!(a.get(2).unwrap());
+    assert_eq!(a.find_first().unwrap(), Some(2));
+    assert_eq!(a.sum().unwrap(), 1);
+
+    a.set_raw(&[0b1100_0000, 0b0000_0001]).unwrap();
+    b.set_raw(&[0b1010_0000]).unwrap();
+    assert_eq!(a.sum_range(1..=15, BitUnit::Bit).unwrap(), 2);
+    assert_eq!(a.find(true, 1.., BitUnit::Byte).unwrap(), Some(15));
+    assert_eq!(b.find(false, .., BitUnit::Byte).unwrap(), Some(1));
+    a.xor(&[&b], &c).unwrap();
+    assert_eq!(&c.get_raw().unwrap()[..], &[0b0110_0000, 1]);
+
+    c.set_many(&[1, 3, 1000], true).unwrap();
+    assert_eq!(c.ones().unwrap().collect::<Vec<_>>(), vec![1, 2, 3, 15, 1000]);
+
+    let field = c.bitfield(BitFieldType::Unsigned(8)).overflow(Overflow::Sat);
+    assert_eq!(field.set(1, 200).unwrap(), Some(1));
+    assert_eq!(field.increment(1, 100).unwrap(), Some(255));
+    assert_eq!(c.bitfield(BitFieldType::Signed(4)).get(2).unwrap(), -1);
+}
+
+#[test]
+fn fake_out_of_range() {
+    let server = FakeRedis::new();
+    let client = &server;
+    let bits = BitVec::new(&server, &b"bits"[..]);
+    assert_eq!(bits.set(1 << 32, true).unwrap_err().to_string(), "redis error: ERR bit offset is not an integer or out of range");
+    assert!(bits.bitfield(BitFieldType::Unsigned(8)).get(1 << 29).is_err());
+
+    assert_eq!(client.arg(b"set").arg(b"x").arg(b"1").fetch().unwrap().text(), "OK");
+    let e = client.arg(b"expire").arg(b"x").arg(u64::MAX / 100).fetch().unwrap_err();
+    assert_eq!(e.to_string(), "redis error: ERR invalid expire time in 'expire' command");
+    assert!(client.arg(b"set").arg(b"x").arg(b"2").arg(b"ex").arg(u64::MAX / 100).fetch().is_err());
+    assert_eq!(client.arg(b"get").arg(b"x").fetch().unwrap().as_bytes(), b"1");
+}
+
+#[test]
+fn mock_connection() {
+    let mut mock = MockConnection::new();
+    mock.expect(&[b"RPUSH", b"list", b"1"]).reply(Response::Integer(1))
+        .expect(&[b"llen", b"list"]).reply(Response::Integer(1))
+        .expect(&[b"rpop", b"list"]).reply(Response::Nothing)
+        .expect(&[b"lset", b"list", b"5", b"1"]).error("ERR index out of range");
+    let conn = RefCell::new(mock);
+    let list = List::new(&conn, &b"list"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+
+    list.push(1).unwrap();
+    assert_eq!(list.len().unwrap(), 1);
+    assert_eq!(list.pop().unwrap(), None);
+    assert!(list.set(5, 1).is_err());
+    conn.borrow().verify();
+    assert_eq!(conn.borrow().commands().len(), 4);
+}
+
+#[test]
+#[should_panic(expected = r#"unexpected command "rpush" "list" "2": expected "rpush" "list" "1""#)]
+fn mock_connection_mismatch() {
+    let mut mock = MockConnection::new();
+    mock.expect(&[b"rpush", b"list", b"1"]).reply(Response::Integer(1));
+    let conn = RefCell::new(mock);
+    let list = List::new(&conn, &b"list"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    list.push(2).unwrap();
+}
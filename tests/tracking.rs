@@ -0,0 +1,78 @@
+use redis_alchemy::*;
+use std::net::TcpStream;
+use std::cell::RefCell;
+use std::time::Duration;
+
+#[test]
+fn client_cache() {
+    for mode in [TrackingMode::Default, TrackingMode::Broadcast(vec![b"tracking"[..].into()])] {
+        let cache = ClientCache::new(16, TcpStream::connect("127.0.0.1:6379").unwrap()).unwrap();
+        let conn = RefCell::new(cache.track(TcpStream::connect("127.0.0.1:6379").unwrap(), &mode).unwrap());
+        let client = TcpClient::new("127.0.0.1:6379");
+
+        let cached = Cell::new(&conn, &b"tracking_cell"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+        let other = Cell::new(&client, &b"tracking_cell"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+
+        cached.set(1).unwrap();
+        assert_eq!(cached.get().unwrap(), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cached.get().unwrap(), 1);
+
+        other.set(2).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(cache.is_empty());
+        assert_eq!(cached.get().unwrap(), 2);
+
+        cached.set(3).unwrap(); // own writes are evicted immediately
+        assert_eq!(cached.get().unwrap(), 3);
+    }
+}
+
+#[test]
+fn client_cache_multi() {
+    let cache = ClientCache::new(16, TcpStream::connect("127.0.0.1:6379").unwrap()).unwrap();
+    let conn = RefCell::new(cache.track(TcpStream::connect("127.0.0.1:6379").unwrap(), &TrackingMode::Default).unwrap());
+    let cached = Cell::new(&conn, &b"tracking_multi"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+
+    cached.set(1).unwrap();
+    assert_eq!(cached.get().unwrap(), 1);
+    assert_eq!(cache.len(), 1);
+
+    // a cached read inside a transaction is still queued on the server, and `QUEUED` is not cached
+    let mut sess = Session::new(conn.borrow_mut());
+    assert_eq!(sess.arg(b"multi").fetch().unwrap().text(), "OK");
+    assert_eq!(sess.arg(b"get").arg(b"tracking_multi").fetch().unwrap().text(), "QUEUED");
+    let res = sess.arg(b"exec").fetch().unwrap().list();
+    assert_eq!(res[0].as_bytes(), b"1");
+    drop(sess);
+
+    assert_eq!(cached.get().unwrap(), 1);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn client_cache_watch() {
+    let cache = ClientCache::new(16, TcpStream::connect("127.0.0.1:6379").unwrap()).unwrap();
+    let conn = RefCell::new(cache.track(TcpStream::connect("127.0.0.1:6379").unwrap(), &TrackingMode::Default).unwrap());
+    let client = TcpClient::new("127.0.0.1:6379");
+    let cached = Cell::new(&conn, &b"tracking_watch"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+    let other = Cell::new(&client, &b"tracking_watch"[..], |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap());
+
+    cached.set(1).unwrap();
+    assert_eq!(cached.get().unwrap(), 1);
+    other.set(2).unwrap(); // the invalidation may not have arrived yet
+
+    // after WATCH the read must see the current value, or the transaction would succeed on a stale one
+    let mut sess = Session::new(conn.borrow_mut());
+    assert_eq!(sess.arg(b"watch").arg(b"tracking_watch").fetch().unwrap().text(), "OK");
+    assert_eq!(sess.arg(b"get").arg(b"tracking_watch").fetch().unwrap().as_bytes(), b"2");
+    assert_eq!(sess.arg(b"multi").fetch().unwrap().text(), "OK");
+    assert_eq!(sess.arg(b"incr").arg(b"tracking_watch").fetch().unwrap().text(), "QUEUED");
+    assert_eq!(sess.arg(b"exec").fetch().unwrap().list()[0].as_integer(), 3);
+    drop(sess);
+
+    // reads are cached again after EXEC
+    assert_eq!(cached.get().unwrap(), 3);
+    assert_eq!(cached.get().unwrap(), 3);
+    assert_eq!(cache.len(), 1);
+}
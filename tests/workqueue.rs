@@ -0,0 +1,66 @@
+use redis_alchemy::*;
+use std::time::Duration;
+
+#[test]
+fn workqueue() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let new_queue = |consumer| WorkQueue::new(&client, &b"workqueue"[..], consumer, |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap())
+        .visibility_timeout(Duration::from_millis(200))
+        .max_retries(1);
+    let queue = new_queue("alice");
+    let crashed = new_queue("bob");
+    queue.clear().unwrap();
+    crashed.clear().unwrap();
+
+    for i in 1..=3 {
+        queue.push(i).unwrap()
+    }
+    assert_eq!(queue.len().unwrap(), 3);
+
+    let job = queue.recv(Duration::from_secs(1)).unwrap().unwrap();
+    assert_eq!(job.item, 1);
+    queue.ack(&job).unwrap();
+
+    let job = queue.recv(Duration::from_secs(1)).unwrap().unwrap();
+    assert_eq!(job.item, 2);
+    queue.fail(&job).unwrap(); // retried once
+    assert_eq!(queue.len().unwrap(), 2);
+
+    let job = crashed.recv(Duration::from_secs(1)).unwrap().unwrap();
+    assert_eq!(job.item, 3);
+    assert_eq!(queue.recover().unwrap(), 0); // bob is still alive
+    std::thread::sleep(Duration::from_millis(300));
+    queue.heartbeat().unwrap();
+    assert_eq!(queue.recover().unwrap(), 1);
+    assert_eq!(queue.len().unwrap(), 2);
+
+    let job = queue.recv(Duration::from_secs(1)).unwrap().unwrap();
+    assert_eq!(job.item, 3); // recovered jobs go to the front
+    queue.fail(&job).unwrap(); // exceeds max_retries
+    let job = queue.recv(Duration::from_secs(1)).unwrap().unwrap();
+    assert_eq!(job.item, 2);
+    queue.fail(&job).unwrap();
+
+    assert!(queue.recv(Duration::from_millis(100)).unwrap().is_none());
+    let mut dead = queue.dead_letters().unwrap();
+    dead.sort();
+    assert_eq!(dead, vec![2, 3]);
+}
+
+#[test]
+fn workqueue_long_recv() {
+    let client = TcpClient::new("127.0.0.1:6379");
+    let new_queue = |consumer| WorkQueue::new(&client, &b"workqueue_long_recv"[..], consumer, |x: &i32| x.to_string().into_bytes().into(), |x| std::str::from_utf8(x).unwrap().parse().unwrap())
+        .visibility_timeout(Duration::from_millis(200));
+    let queue = new_queue("alice");
+    let waiting = new_queue("bob");
+    queue.clear().unwrap();
+    waiting.clear().unwrap();
+
+    queue.push(1).unwrap();
+    assert_eq!(waiting.recv(Duration::from_secs(1)).unwrap().unwrap().item, 1);
+    let handle = oh_my_rust::scoped_spawn(|| assert!(waiting.recv(Duration::from_millis(800)).unwrap().is_none()));
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(queue.recover().unwrap(), 0); // bob keeps sending heartbeats while blocked
+    handle.join().unwrap()
+}